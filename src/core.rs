@@ -2,11 +2,14 @@
 mod config;
 /// CLI argument definitions.
 pub mod interface;
+/// Secret redaction applied to outgoing prompts.
+mod redact;
 mod router;
 pub mod theme;
 /// Trace server for raw LLM traffic.
 pub mod trace;
 
-pub use config::Config;
-pub use interface::{Cli, Commands};
-pub use router::Service;
+pub use config::{Config, env_flag, env_number};
+pub use interface::{Cli, Commands, SessionCommands, TemplateCommands};
+pub use redact::Redactor;
+pub use router::{Image, RequestOptions, Service};
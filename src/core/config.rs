@@ -5,6 +5,10 @@ pub struct Config {
     pub endpoint: String,
     pub model: String,
     pub apikey: Option<String>,
+    /// True when the model accepts image content parts (`NETERO_VISION`).
+    pub vision: bool,
+    /// Redact secrets from prompts, disabled with `--no-redact`.
+    pub redact: bool,
     pub verbose: bool,
 }
 
@@ -40,7 +44,63 @@ impl Config {
             endpoint,
             model,
             apikey,
+            vision: env_flag("NETERO_VISION"),
+            redact: !args.no_redact,
             verbose: args.verbose,
         }
     }
+
+    /// Loads a named backend from `NETERO_PROFILE_<NAME>_URL`, `_MODEL`,
+    /// `_API_KEY` and `_VISION`, where `<NAME>` is uppercased with `-` as `_`.
+    pub fn from_profile(args: &Cli, name: &str) -> Result<Self, String> {
+        let prefix = format!("NETERO_PROFILE_{}_", name.to_uppercase().replace('-', "_"));
+        let var = |key: &str| {
+            std::env::var(format!("{}{}", prefix, key))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        };
+        let (Some(endpoint), Some(model)) = (var("URL"), var("MODEL")) else {
+            return Err(format!(
+                "profile '{}' needs {}URL and {}MODEL",
+                name, prefix, prefix
+            ));
+        };
+
+        Ok(Self {
+            endpoint,
+            model,
+            apikey: var("API_KEY"),
+            vision: env_flag(&format!("{}VISION", prefix)),
+            redact: !args.no_redact,
+            verbose: args.verbose,
+        })
+    }
+
+    /// Names of the profiles with a `NETERO_PROFILE_<NAME>_URL` variable,
+    /// lowercased with `_` as `-`.
+    pub fn profile_names() -> Vec<String> {
+        let mut names: Vec<String> = std::env::vars()
+            .filter_map(|(key, _)| {
+                let name = key.strip_prefix("NETERO_PROFILE_")?.strip_suffix("_URL")?;
+                Some(name.to_lowercase().replace('_', "-"))
+            })
+            .filter(|name| !name.is_empty())
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+/// True when the variable is `1`, `true`, `on` or `yes`.
+pub fn env_flag(key: &str) -> bool {
+    std::env::var(key).is_ok_and(|v| matches!(v.trim(), "1" | "true" | "on" | "yes"))
+}
+
+/// Positive number from an environment variable; `None` when unset, zero or
+/// not a number, so callers fall back to their default.
+pub fn env_number(key: &str) -> Option<usize> {
+    std::env::var(key)
+        .ok()
+        .and_then(|v| v.trim().parse::<usize>().ok())
+        .filter(|n| *n > 0)
 }
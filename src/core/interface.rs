@@ -22,12 +22,28 @@ pub struct Cli {
     /// Start a trace server to print raw LLM traffic
     #[arg(short = 't', long)]
     pub trace: bool,
+
+    /// Print only a fenced code block of the response (code[:lang|:N])
+    #[arg(short = 'x', long, global = true, value_name = "SPEC")]
+    pub extract: Option<String>,
+
+    /// Chat persona from the config directory's personas/<NAME>.md
+    #[arg(long, global = true, value_name = "NAME", env = "NETERO_PERSONA")]
+    pub persona: Option<String>,
+
+    /// Send prompts without redacting secrets
+    #[arg(long, global = true)]
+    pub no_redact: bool,
 }
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Open a minimal chat session
-    Chat,
+    Chat {
+        /// Resume a saved session by id, or the latest one when no id is given
+        #[arg(long, value_name = "ID", num_args = 0..=1, default_missing_value = "last")]
+        resume: Option<String>,
+    },
 
     /// Generate a commit message
     Commit {
@@ -45,6 +61,51 @@ pub enum Commands {
         input: Vec<String>,
     },
 
+    /// Send a prompt template with its variables filled in
+    Run {
+        /// Template name, from the config directory's templates/<NAME>.md
+        template: String,
+        /// Variable value (KEY=VALUE); `-` reads stdin, `@path` a file
+        #[arg(long = "var", value_name = "KEY=VALUE")]
+        vars: Vec<String>,
+        /// Values for the remaining variables, in declaration order
+        values: Vec<String>,
+    },
+
+    /// Manage prompt templates
+    Templates {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+
+    /// Manage saved chat sessions
+    Sessions {
+        #[command(subcommand)]
+        command: SessionCommands,
+    },
+
     /// Generate shell completion
     Completion { shell: clap_complete::Shell },
 }
+
+#[derive(Subcommand, Debug)]
+pub enum TemplateCommands {
+    /// List the available templates
+    List,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommands {
+    /// Print or write the transcript of a saved session
+    Export {
+        /// Session id, or `last` for the most recent one
+        #[arg(default_value = "last")]
+        id: String,
+        /// Transcript format: md, json or html
+        #[arg(short, long, value_name = "FORMAT")]
+        format: Option<String>,
+        /// File to write instead of printing the transcript
+        #[arg(short, long, value_name = "PATH")]
+        output: Option<String>,
+    },
+}
@@ -0,0 +1,171 @@
+use regex::{Captures, Regex};
+use std::collections::BTreeSet;
+
+/// Minimum length of a token considered by the entropy check.
+const ENTROPY_MIN_LEN: usize = 32;
+/// Bits per character above which a token is treated as a secret.
+/// Hex digests (at most 4 bits) such as commit hashes stay below it.
+const ENTROPY_THRESHOLD: f64 = 4.3;
+/// Rule for `NAME=value` assignments, whose values are checked further.
+const ASSIGNMENT_RULE: &str = "env-assignment";
+
+/// Built-in secret formats. A `value` group limits the replacement to that part.
+const BUILTIN_RULES: [(&str, &str); 10] = [
+    (
+        "private-key",
+        r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----",
+    ),
+    (
+        "aws-access-key",
+        r"\b(?:AKIA|ASIA|AGPA|AIDA|AROA|ANPA|ANVA|AIPA)[A-Z0-9]{16}\b",
+    ),
+    (
+        "github-token",
+        r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{22,})\b",
+    ),
+    ("slack-token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+    ("api-key", r"\bsk-[A-Za-z0-9_-]{20,}"),
+    (
+        "jwt",
+        r"\beyJ[A-Za-z0-9_-]{8,}\.eyJ[A-Za-z0-9_-]{8,}\.[A-Za-z0-9_-]{8,}",
+    ),
+    ("url-credentials", r"://[^/\s:@]+:(?P<value>[^/\s@]+)@"),
+    // Assignments on their own line, as in `.env` files and shell scripts;
+    // comparisons (`==`) and short values are left alone.
+    (
+        ASSIGNMENT_RULE,
+        r#"(?m)^[ \t]*(?:export[ \t]+)?[A-Z0-9_]*(?:PASSWORD|PASSWD|SECRET|TOKEN|API_?KEY|ACCESS_?KEY|PRIVATE_?KEY|CREDENTIALS?)[A-Z0-9_]*[ \t]*=[ \t]*["']?(?P<value>[^\s"'=][^\s"']{7,})"#,
+    ),
+    (
+        "password",
+        r#"(?i)\b(?:password|passwd|secret|api[_-]?key|access[_-]?token|auth[_-]?token)["']?\s*[:=]\s*["'](?P<value>[^"'\s]{4,})["']"#,
+    ),
+    // Slashes are left out so file paths are never taken for secrets.
+    ("high-entropy", r"[A-Za-z0-9+_-]{32,}={0,2}"),
+];
+
+/// Replaces secrets in outgoing prompts with `[REDACTED:<kind>]` placeholders.
+#[derive(Clone)]
+pub struct Redactor {
+    rules: Vec<(String, Regex)>,
+}
+
+impl Redactor {
+    /// Builds the built-in rules plus custom regexes from `NETERO_REDACT_PATTERNS`
+    /// (one per line). Invalid custom patterns are reported and ignored.
+    pub fn from_env() -> Self {
+        let mut rules: Vec<(String, Regex)> = BUILTIN_RULES
+            .iter()
+            .map(|(name, pattern)| (name.to_string(), Regex::new(pattern).unwrap()))
+            .collect();
+        let custom = std::env::var("NETERO_REDACT_PATTERNS").unwrap_or_default();
+        for pattern in custom.lines().map(str::trim).filter(|p| !p.is_empty()) {
+            match Regex::new(pattern) {
+                // Custom rules run before the entropy check, which stays last.
+                Ok(regex) => rules.insert(rules.len() - 1, ("custom".to_string(), regex)),
+                Err(err) => eprintln!(
+                    "Invalid NETERO_REDACT_PATTERNS entry '{}': {}",
+                    pattern, err
+                ),
+            }
+        }
+        Self { rules }
+    }
+
+    /// Returns the redacted text and the kinds of secrets that were replaced.
+    pub fn redact(&self, text: &str) -> (String, BTreeSet<String>) {
+        let mut found = BTreeSet::new();
+        let mut out = text.to_string();
+        for (name, regex) in &self.rules {
+            let replaced = regex.replace_all(&out, |caps: &Captures| {
+                let whole = caps.get(0).unwrap();
+                let target = caps.name("value").unwrap_or(whole).as_str();
+                // Leave secrets already replaced by an earlier rule alone.
+                if target.starts_with("[REDACTED:")
+                    || (name == "high-entropy" && !is_high_entropy(target))
+                    // Numbers such as `MAX_TOKENS=4096` are settings, not secrets.
+                    || (name == ASSIGNMENT_RULE && target.chars().all(|ch| ch.is_ascii_digit()))
+                {
+                    return whole.as_str().to_string();
+                }
+                let kind = if name == ASSIGNMENT_RULE {
+                    "password"
+                } else {
+                    name
+                };
+                found.insert(kind.to_string());
+                let placeholder = format!("[REDACTED:{}]", kind);
+                match caps.name("value") {
+                    Some(value) => {
+                        let start = value.start() - whole.start();
+                        let end = value.end() - whole.start();
+                        let whole = whole.as_str();
+                        format!("{}{}{}", &whole[..start], placeholder, &whole[end..])
+                    }
+                    None => placeholder,
+                }
+            });
+            out = replaced.into_owned();
+        }
+        (out, found)
+    }
+}
+
+/// True for long mixed-character tokens with high Shannon entropy.
+fn is_high_entropy(token: &str) -> bool {
+    let token = token.trim_end_matches('=');
+    if token.len() < ENTROPY_MIN_LEN
+        || !token.chars().any(|ch| ch.is_ascii_digit())
+        || !token.chars().any(|ch| ch.is_ascii_alphabetic())
+    {
+        return false;
+    }
+    let mut counts = [0usize; 256];
+    for byte in token.bytes() {
+        counts[byte as usize] += 1;
+    }
+    let len = token.len() as f64;
+    let entropy: f64 = counts
+        .iter()
+        .filter(|count| **count > 0)
+        .map(|count| {
+            let p = *count as f64 / len;
+            -p * p.log2()
+        })
+        .sum();
+    entropy >= ENTROPY_THRESHOLD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redact(text: &str) -> String {
+        Redactor::from_env().redact(text).0
+    }
+
+    #[test]
+    fn redacts_secret_assignments() {
+        assert_eq!(
+            redact("DB_PASSWORD=hunter2hunter2\n"),
+            "DB_PASSWORD=[REDACTED:password]\n"
+        );
+        assert_eq!(
+            redact("  export API_TOKEN=\"abcd1234efgh\""),
+            "  export API_TOKEN=\"[REDACTED:password]\""
+        );
+    }
+
+    #[test]
+    fn leaves_code_alone() {
+        for text in [
+            "MAX_TOKENS = 4096",
+            "if FOO_TOKEN == x:",
+            "let total = MAX_TOKENS = 10;",
+            "SECRET=short",
+            "x = API_KEY=notanassignment",
+        ] {
+            assert_eq!(redact(text), text);
+        }
+    }
+}
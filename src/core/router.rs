@@ -1,26 +1,70 @@
+use crate::core::redact::Redactor;
 use crate::core::trace::send_trace;
 use crate::core::{Cli, Config};
 
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
+#[derive(Clone)]
 pub struct Service {
     pub http: Client,
     pub apikey: Option<String>,
     pub endpoint: String,
     pub model: String,
+    pub vision: bool,
+    redactor: Option<Redactor>,
+}
+
+/// Image sent along with a prompt.
+pub struct Image {
+    /// Name shown in traces, usually the attached path.
+    pub label: String,
+    /// `data:<mime>;base64,...` URL.
+    pub data_url: String,
 }
 
 #[derive(Serialize)]
 pub struct Message {
     pub role: String,
-    pub content: String,
+    pub content: Content,
+}
+
+/// Plain text, or content parts when images are attached.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Content {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// OpenAI-compatible multimodal content part.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+#[derive(Serialize)]
+pub struct ImageUrl {
+    pub url: String,
 }
 
 #[derive(Serialize)]
 pub struct ChatRequest {
     pub model: String,
     pub messages: Vec<Message>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub stream: bool,
+}
+
+/// Per-request overrides of the configured model and sampling.
+#[derive(Clone, Default)]
+pub struct RequestOptions {
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
 }
 
 #[derive(Deserialize)]
@@ -40,8 +84,15 @@ pub struct ResponseMessage {
 
 impl Service {
     pub fn new(args: &Cli) -> Self {
-        let config = Config::from_env(args);
+        Self::from_config(Config::from_env(args))
+    }
 
+    /// Builds a service for a named profile (see `Config::from_profile`).
+    pub fn from_profile(args: &Cli, name: &str) -> Result<Self, String> {
+        Config::from_profile(args, name).map(Self::from_config)
+    }
+
+    fn from_config(config: Config) -> Self {
         if config.verbose {
             println!("modelo: {:#?}\nurl: {:#?}\n", config.model, config.endpoint);
         }
@@ -51,20 +102,112 @@ impl Service {
             apikey: config.apikey,
             endpoint: config.endpoint,
             model: config.model,
+            vision: config.vision,
+            redactor: config.redact.then(Redactor::from_env),
         }
     }
 
-    pub async fn complete(&self, content: &str) -> Result<String, Box<dyn std::error::Error>> {
-        // Send request/response to the trace server when enabled.
-        send_trace(":: REQUEST ::", content).await;
+    /// Fails when images are sent to a model not marked as vision-capable.
+    pub fn check_images(&self, images: &[Image]) -> Result<(), String> {
+        if images.is_empty() || self.vision {
+            return Ok(());
+        }
+        Err(format!(
+            "model '{}' is not marked as vision-capable, set NETERO_VISION=1 to send images",
+            self.model
+        ))
+    }
 
-        let body = ChatRequest {
-            model: self.model.clone(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: content.to_string(),
-            }],
+    /// Redactor for outgoing text, `None` with `--no-redact`.
+    pub fn redactor(&self) -> Option<&Redactor> {
+        self.redactor.as_ref()
+    }
+
+    /// Replaces secrets in outgoing text, reporting what was redacted on stderr.
+    fn redact(&self, content: &str) -> String {
+        let Some(redactor) = &self.redactor else {
+            return content.to_string();
         };
+        let (redacted, found) = redactor.redact(content);
+        if !found.is_empty() {
+            eprintln!(
+                "redacted secrets ({}), use --no-redact to send them as-is",
+                found.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+        redacted
+    }
+
+    /// Builds the user message with secrets redacted, as content parts when
+    /// images are attached.
+    pub fn user_message(&self, content: &str, images: &[Image]) -> Result<Message, String> {
+        self.check_images(images)?;
+        let content = &self.redact(content);
+        if images.is_empty() {
+            return Ok(Message {
+                role: "user".to_string(),
+                content: Content::Text(content.to_string()),
+            });
+        }
+        let mut parts = vec![ContentPart::Text {
+            text: content.to_string(),
+        }];
+        parts.extend(images.iter().map(|image| ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: image.data_url.clone(),
+            },
+        }));
+        Ok(Message {
+            role: "user".to_string(),
+            content: Content::Parts(parts),
+        })
+    }
+
+    /// Builds a single-message request, applying per-request overrides.
+    pub fn chat_request(
+        &self,
+        content: &str,
+        images: &[Image],
+        options: &RequestOptions,
+    ) -> Result<ChatRequest, String> {
+        Ok(ChatRequest {
+            model: options.model.clone().unwrap_or_else(|| self.model.clone()),
+            messages: vec![self.user_message(content, images)?],
+            temperature: options.temperature,
+            stream: false,
+        })
+    }
+
+    pub async fn complete(&self, content: &str) -> Result<String, Box<dyn std::error::Error>> {
+        self.complete_with(content, &[], &RequestOptions::default())
+            .await
+    }
+
+    /// Sends a prompt with optional images attached as content parts.
+    pub async fn complete_with_images(
+        &self,
+        content: &str,
+        images: &[Image],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.complete_with(content, images, &RequestOptions::default())
+            .await
+    }
+
+    /// Sends a prompt with images and per-request overrides.
+    pub async fn complete_with(
+        &self,
+        content: &str,
+        images: &[Image],
+        options: &RequestOptions,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let body = self.chat_request(content, images, options)?;
+
+        // Send request/response to the trace server when enabled, after redaction.
+        send_trace(
+            ":: REQUEST ::",
+            &trace_payload(&body.messages[0].content, images),
+        )
+        .await;
 
         let mut req = self.http.post(&self.endpoint).json(&body);
 
@@ -77,7 +220,7 @@ impl Service {
         // Extract the first assistant message from the response.
         let content = response
             .choices
-            .get(0)
+            .first()
             .ok_or("No choices returned")?
             .message
             .content
@@ -88,3 +231,22 @@ impl Service {
         Ok(content)
     }
 }
+
+/// Request text for the trace server, with images listed instead of their data.
+fn trace_payload(content: &Content, images: &[Image]) -> String {
+    let mut payload = match content {
+        Content::Text(text) => text.clone(),
+        Content::Parts(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                ContentPart::Text { text } => Some(text.as_str()),
+                ContentPart::ImageUrl { .. } => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    for image in images {
+        payload.push_str(&format!("\n[image: {}]", image.label));
+    }
+    payload
+}
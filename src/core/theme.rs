@@ -1,4 +1,5 @@
 //! Color theme helpers (not wired into output yet).
+#![allow(dead_code)]
 use std::env;
 
 /// Terminal theme preference.
@@ -78,15 +79,14 @@ fn is_no_color() -> bool {
 
 /// Best-effort theme detection from common terminal env vars.
 fn detect_theme() -> Theme {
-    if let Ok(value) = env::var("COLORFGBG") {
-        if let Some(bg) = value.split(';').last() {
-            if let Ok(code) = bg.parse::<i32>() {
-                if code >= 7 {
-                    return Theme::Light;
-                }
-                return Theme::Dark;
-            }
+    if let Ok(value) = env::var("COLORFGBG")
+        && let Some(bg) = value.split(';').next_back()
+        && let Ok(code) = bg.parse::<i32>()
+    {
+        if code >= 7 {
+            return Theme::Light;
         }
+        return Theme::Dark;
     }
 
     if let Ok(value) = env::var("TERM") {
@@ -8,6 +8,7 @@ use clap_complete::generate;
 use tasks::chat;
 use tasks::commit;
 use tasks::pipeline;
+use tasks::template;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -24,6 +25,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .exit();
     }
 
+    // Only prompts and `run` send a request whose answer can be extracted.
+    let extractable = match &args.command {
+        Some(core::Commands::Prompt { .. } | core::Commands::Run { .. }) => true,
+        Some(_) => false,
+        None => !args.prompt.is_empty(),
+    };
+    if args.extract.is_some() && !extractable {
+        let mut cmd = core::Cli::command();
+        cmd.error(
+            clap::error::ErrorKind::ArgumentConflict,
+            "--extract only applies to prompts and `run`",
+        )
+        .exit();
+    }
+
     if args.trace {
         core::trace::run_trace_server().await?;
         return Ok(());
@@ -55,14 +71,29 @@ async fn execute(
             let input_text = input.join(" ");
             pipeline::connect(service, args, &input_text, stdin).await?
         }
-        Some(core::Commands::Chat) => chat::connect(service, args, stdin, stdin_is_piped).await,
+        Some(core::Commands::Chat { resume }) => {
+            chat::connect(service, args, stdin, stdin_is_piped, resume.as_deref()).await
+        }
+        Some(core::Commands::Run {
+            template,
+            vars,
+            values,
+        }) => template::run_template(service, args, template, vars, values, stdin).await?,
+        Some(core::Commands::Templates { command }) => match command {
+            core::TemplateCommands::List => template::list_templates(),
+        },
+        Some(core::Commands::Sessions { command }) => match command {
+            core::SessionCommands::Export { id, format, output } => {
+                chat::export_session(id, format.as_deref(), output.as_deref())?
+            }
+        },
         Some(core::Commands::Completion { shell }) => {
             let mut cmd = core::Cli::command();
             generate(*shell, &mut cmd, "netero", &mut std::io::stdout());
         }
         None => {
             if args.prompt.is_empty() {
-                chat::connect(service, args, stdin, stdin_is_piped).await;
+                chat::connect(service, args, stdin, stdin_is_piped, None).await;
             } else {
                 let prompt_text = args.prompt.join(" ");
                 pipeline::connect(service, args, &prompt_text, stdin).await?;
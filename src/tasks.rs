@@ -5,7 +5,13 @@ pub mod attach;
 pub mod chat;
 /// Commit message generation task.
 pub mod commit;
+/// Code block extraction from model responses.
+pub mod extract;
+/// Inline command and file reference expansion.
+pub mod inline;
 /// Single prompt pipeline task.
 pub mod pipeline;
 /// Shared helpers for task output.
 pub mod render;
+/// Prompt templates shared by `run` and chat.
+pub mod template;
@@ -1,32 +1,217 @@
+mod dir;
+mod document;
+mod image;
+mod range;
+mod read;
+
+pub use image::ImageData;
+pub use range::parse_line_range;
+
 use std::env;
 use std::fs;
+use std::path::Path;
+
+use crate::core;
+use crate::utils::format_size;
+
+/// Maximum attachment names listed in the summary line.
+const SUMMARY_NAMES: usize = 8;
 
 /// File attachment extracted from user input.
 pub struct Attachment {
     /// Path as written by the user (not expanded).
     pub path: String,
     pub content: String,
+    /// Inclusive 1-based line range when only part of the file is attached.
+    pub range: Option<(usize, usize)>,
+    /// Truncation or decoding note shown in the attachment summary.
+    pub note: Option<String>,
+    /// Image sent as a content part; `content` then only describes it.
+    pub image: Option<ImageData>,
 }
 
-/// Returns true if the token looks like a file path.
+impl Attachment {
+    /// Bytes sent for this attachment, the encoded size for images.
+    pub fn size(&self) -> usize {
+        self.image
+            .as_ref()
+            .map_or(self.content.len(), |image| image.bytes.len())
+    }
+
+    /// Label used in attachment headers, including the line range if any.
+    pub fn label(&self) -> String {
+        let unit = if document::is_document_path(&self.path) {
+            "pages"
+        } else {
+            "lines"
+        };
+        match self.range {
+            Some((start, end)) => format!("{} ({} {}-{})", self.path, unit, start, end),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Returns true if the token looks like a file path. A lone `/` or `~/` is
+/// prose (`10 / 2`) far more often than a request to attach a whole tree.
 fn is_path_candidate(token: &str) -> bool {
+    if token == "/" || token == "~/" {
+        return false;
+    }
     token.starts_with('/')
         || token.starts_with("./")
         || token.starts_with("../")
         || token.starts_with("~/")
 }
 
-fn expand_path(path: &str) -> String {
-    if let Some(rest) = path.strip_prefix("~/") {
-        if let Ok(home) = env::var("HOME") {
-            return format!("{}/{}", home, rest);
-        }
+/// True when a token naming a directory clearly asks for its contents: a
+/// trailing `/` or an explicit relative path such as `./src`.
+fn is_dir_reference(token: &str) -> bool {
+    token.ends_with('/') || token.starts_with("./") || token.starts_with("../")
+}
+
+/// Whether a path token points at something on disk, or `None` when the token
+/// does not look like a path. Selectors are ignored and globs check their base.
+pub fn path_exists(token: &str) -> Option<bool> {
+    if !is_path_candidate(token) {
+        return None;
+    }
+    let exists = |path: &str| Path::new(&expand_path(path)).exists();
+    if dir::is_glob(token) {
+        return Some(exists(&dir::glob_base(token)));
+    }
+    Some(exists(token) || exists(range::split_selector(token).0))
+}
+
+/// Expands a leading `~/` to the user home directory.
+pub fn expand_path(path: &str) -> String {
+    if let Some(rest) = path.strip_prefix("~/")
+        && let Ok(home) = env::var("HOME")
+    {
+        return format!("{}/{}", home, rest);
     }
     path.to_string()
 }
 
-fn read_file(path: &str) -> Option<String> {
-    fs::read_to_string(path).ok()
+/// Resolves a file, directory or glob into attachments.
+/// Directories and globs respect `.gitignore` and skip binary files.
+/// Absolute and home directories need a trailing `/` to be attached.
+pub fn attach_path(token: &str) -> Result<Vec<Attachment>, String> {
+    let expanded = expand_path(token);
+    if dir::is_glob(token) {
+        return match dir::attach_glob(token, &expanded) {
+            Some(found) if found.len() > 1 => Ok(found),
+            Some(_) => Err("no files match".to_string()),
+            None => Err("invalid glob or missing base directory".to_string()),
+        };
+    }
+
+    let meta = match fs::metadata(&expanded) {
+        Ok(meta) => meta,
+        // `./file.rs:120-180`, `./file.rs:120` and `./file.rs#name` select part of a file.
+        Err(err) => match range::split_selector(token) {
+            (path, Some(selector)) => return attach_part(path, &selector),
+            _ => return Err(err.to_string()),
+        },
+    };
+    if meta.is_dir() {
+        if !is_dir_reference(token) {
+            return Err("is a directory, add a trailing / to attach it".to_string());
+        }
+        return Ok(dir::attach_dir(token, &expanded));
+    }
+    // Devices and FIFOs never end, or block until something writes to them.
+    if !meta.is_file() {
+        return Err("not a regular file".to_string());
+    }
+    read_file(token).map(|attachment| vec![attachment])
+}
+
+/// Reads a whole file, truncating it past `NETERO_ATTACH_MAX_BYTES` and
+/// decoding non-UTF-8 text lossily. Binary files are rejected.
+pub fn read_file(path: &str) -> Result<Attachment, String> {
+    if image::is_image_path(path) {
+        let (data, note) = image::read_image(Path::new(&expand_path(path)))?;
+        return Ok(Attachment {
+            path: path.to_string(),
+            content: data.describe(),
+            range: None,
+            note,
+            image: Some(data),
+        });
+    }
+    if document::is_document_path(path) {
+        let pages = document::extract_pages(path, Path::new(&expand_path(path)))?;
+        let text = document::render_pages(&pages, 1, pages.len());
+        let (content, truncated) = read::truncate_text(text, read::max_bytes());
+        return Ok(Attachment {
+            path: path.to_string(),
+            content,
+            range: None,
+            note: truncated.then(|| format!("truncated, {} pages total", pages.len())),
+            image: None,
+        });
+    }
+    let text = read::read_text(Path::new(&expand_path(path)), read::max_bytes())?;
+    Ok(Attachment {
+        path: path.to_string(),
+        note: text.note(),
+        content: text.content,
+        range: None,
+        image: None,
+    })
+}
+
+/// Reads an inclusive 1-based line range of a file.
+pub fn read_lines(path: &str, start: usize, end: usize) -> Result<Attachment, String> {
+    attach_part(path, &range::Selector::Lines(start, end)).map(|mut found| found.remove(0))
+}
+
+fn attach_part(path: &str, selector: &range::Selector) -> Result<Vec<Attachment>, String> {
+    if document::is_document_path(path) {
+        return attach_pages(path, selector);
+    }
+    // Ranges are taken from the whole file, the size limit applies to the selection.
+    let text = read::read_text(Path::new(&expand_path(path)), usize::MAX)?;
+    let (selected, lines) = range::select(&text.content, path, selector)?;
+    Ok(vec![Attachment {
+        path: path.to_string(),
+        content: selected,
+        range: Some(lines),
+        note: text.note(),
+        image: None,
+    }])
+}
+
+/// Attaches a page range of a document (`./spec.pdf:3-7`).
+fn attach_pages(path: &str, selector: &range::Selector) -> Result<Vec<Attachment>, String> {
+    let (start, end) = match selector {
+        range::Selector::Lines(start, end) => (*start, *end),
+        range::Selector::Line(page) => (*page, *page),
+        range::Selector::Symbol(_) => {
+            return Err("symbol lookup is not supported for documents".to_string());
+        }
+    };
+    let pages = document::extract_pages(path, Path::new(&expand_path(path)))?;
+    if start > pages.len() {
+        return Err(format!(
+            "page {} is past the end of the document ({} pages)",
+            start,
+            pages.len()
+        ));
+    }
+    let end = end.min(pages.len());
+    let (content, truncated) = read::truncate_text(
+        document::render_pages(&pages, start, end),
+        read::max_bytes(),
+    );
+    Ok(vec![Attachment {
+        path: path.to_string(),
+        content,
+        range: Some((start, end)),
+        note: truncated.then(|| "truncated".to_string()),
+        image: None,
+    }])
 }
 
 /// Splits input into tokens, honoring quotes and backslash escapes.
@@ -91,19 +276,16 @@ pub fn extract_attachments_from_tokens(tokens: &[String]) -> (Vec<String>, Vec<A
             continue;
         }
 
-        let expanded = expand_path(token);
-        match fs::metadata(&expanded) {
-            Ok(meta) if meta.is_file() => {
-                if let Some(content) = read_file(&expanded) {
-                    attachments.push(Attachment {
-                        path: token.clone(),
-                        content,
-                    });
-                } else {
-                    remaining.push(token.clone());
+        match attach_path(token) {
+            Ok(found) => attachments.extend(found),
+            Err(err) => {
+                // Only report paths that exist, anything else is just text.
+                let (path, _) = range::split_selector(token);
+                if Path::new(&expand_path(path)).exists() {
+                    eprintln!("\n{}: {}", token, err);
                 }
+                remaining.push(token.clone());
             }
-            _ => remaining.push(token.clone()),
         }
     }
 
@@ -117,19 +299,58 @@ pub fn extract_attachments_from_input(input: &str) -> (String, Vec<Attachment>)
     (input.to_string(), attachments)
 }
 
-/// Formats attachments into a single block, compatible with stdin attachments.
-pub fn format_attachments(attachments: &[Attachment]) -> Option<String> {
+/// Collects image attachments as content parts for the model.
+pub fn images<'a>(attachments: impl IntoIterator<Item = &'a Attachment>) -> Vec<core::Image> {
+    attachments
+        .into_iter()
+        .filter_map(|a| a.image.as_ref().map(|image| image.to_core(&a.path)))
+        .collect()
+}
+
+/// Summarizes what was attached and how much, with truncation and decoding notes.
+pub fn summary(attachments: &[Attachment]) -> Option<String> {
     if attachments.is_empty() {
         return None;
     }
+    let total: usize = attachments.iter().map(Attachment::size).sum();
+    let mut names: Vec<String> = attachments
+        .iter()
+        .take(SUMMARY_NAMES)
+        .map(Attachment::label)
+        .collect();
+    if attachments.len() > SUMMARY_NAMES {
+        names.push(format!("and {} more", attachments.len() - SUMMARY_NAMES));
+    }
+    let mut out = format!(
+        "attached {} file{} ({}): {}",
+        attachments.len(),
+        if attachments.len() == 1 { "" } else { "s" },
+        format_size(total as u64),
+        names.join(", ")
+    );
+    for attachment in attachments {
+        if let Some(note) = &attachment.note {
+            out.push_str(&format!("\n  warning: {}: {}", attachment.label(), note));
+        }
+    }
+    Some(out)
+}
+
+/// Formats attachments into a single block, compatible with stdin attachments.
+pub fn format_attachments<'a>(
+    attachments: impl IntoIterator<Item = &'a Attachment>,
+) -> Option<String> {
     let mut out = String::new();
     for attachment in attachments {
         out.push_str("\n-- FILE: ");
-        out.push_str(&attachment.path);
+        out.push_str(&attachment.label());
         out.push_str(" --\n");
         out.push_str(&attachment.content);
         out.push('\n');
     }
+    if out.is_empty() {
+        return None;
+    }
     Some(out)
 }
 
@@ -158,18 +379,18 @@ fn indent_block(content: &str, prefix: &str) -> String {
 /// Formats stdin and file attachments into a single attached files block.
 pub fn format_attached_files(stdin: Option<&str>, attachments: &[Attachment]) -> Option<String> {
     let mut sections = Vec::new();
-    if let Some(content) = stdin {
-        if !content.trim().is_empty() {
-            sections.push(format!(
-                "-- FILE: STDIN --\n{}",
-                indent_block(content, "      ")
-            ));
-        }
+    if let Some(content) = stdin
+        && !content.trim().is_empty()
+    {
+        sections.push(format!(
+            "-- FILE: STDIN --\n{}",
+            indent_block(content, "      ")
+        ));
     }
     for attachment in attachments {
         sections.push(format!(
             "-- FILE: {} --\n{}",
-            attachment.path,
+            attachment.label(),
             indent_block(&attachment.content, "      ")
         ));
     }
@@ -182,3 +403,18 @@ pub fn format_attached_files(stdin: Option<&str>, attachments: &[Attachment]) ->
     out.push_str("\n\n:: END ATTACHED FILES ::");
     Some(out)
 }
+
+/// Scratch directories for the attachment tests.
+#[cfg(test)]
+mod scratch {
+    use std::path::PathBuf;
+    use std::{env, fs, process};
+
+    /// Empty directory unique to the test and the process.
+    pub fn dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("netero-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+}
@@ -0,0 +1,257 @@
+use globset::{GlobBuilder, GlobMatcher};
+use ignore::WalkBuilder;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use super::Attachment;
+use super::read::{max_bytes, read_text};
+
+/// Maximum number of files attached from one directory or glob.
+const MAX_DIR_FILES: usize = 200;
+/// Maximum total bytes attached from one directory or glob.
+const MAX_DIR_BYTES: u64 = 512 * 1024;
+/// Maximum walked entries, so a glob matching little under a huge tree ends.
+const MAX_WALK_ENTRIES: usize = 50_000;
+
+/// Returns true when the token contains glob metacharacters.
+pub fn is_glob(token: &str) -> bool {
+    token.contains(['*', '?', '['])
+}
+
+/// Splits a glob token into its literal base directory and the pattern below it.
+fn split_glob(token: &str) -> (String, String) {
+    let mut base = Vec::new();
+    let mut parts = token.split('/').peekable();
+    while let Some(part) = parts.next_if(|part| !is_glob(part)) {
+        base.push(part);
+    }
+    let pattern = parts.collect::<Vec<_>>().join("/");
+    let base = if base.is_empty() || (base.len() == 1 && base[0].is_empty()) {
+        // `/...` keeps the root, anything else is relative to the current directory.
+        if token.starts_with('/') { "/" } else { "." }.to_string()
+    } else {
+        base.join("/")
+    };
+    (base, pattern)
+}
+
+/// Literal directory a glob token starts from.
+pub fn glob_base(token: &str) -> String {
+    split_glob(token).0
+}
+
+/// Attaches files under a directory, respecting `.gitignore`.
+pub fn attach_dir(token: &str, expanded: &str) -> Vec<Attachment> {
+    collect(token, Path::new(expanded), None)
+}
+
+/// Attaches files matching a glob such as `./src/**/*.rs`, respecting `.gitignore`.
+/// Returns `None` when the pattern is invalid or its base directory does not exist.
+pub fn attach_glob(token: &str, expanded: &str) -> Option<Vec<Attachment>> {
+    let (display_base, _) = split_glob(token);
+    let (base, pattern) = split_glob(expanded);
+    if !Path::new(&base).is_dir() {
+        return None;
+    }
+    let matcher = GlobBuilder::new(&pattern)
+        .literal_separator(true)
+        .build()
+        .ok()?
+        .compile_matcher();
+    // Without `**` nothing deeper than the pattern can match.
+    let depth = (!pattern.contains("**")).then(|| pattern.split('/').count());
+    Some(collect(
+        &display_base,
+        Path::new(&base),
+        Some((&matcher, depth)),
+    ))
+}
+
+/// Walks `root` in path order, returning a tree listing attachment followed by
+/// file attachments. The walk stops as soon as a limit is reached. A glob
+/// matcher comes with the deepest level it can match, if bounded.
+fn collect(
+    display_root: &str,
+    root: &Path,
+    matcher: Option<(&GlobMatcher, Option<usize>)>,
+) -> Vec<Attachment> {
+    let display_root = display_root.trim_end_matches('/');
+    let walk = WalkBuilder::new(root)
+        .require_git(false)
+        .max_depth(matcher.and_then(|(_, depth)| depth))
+        .sort_by_file_name(|a, b| a.cmp(b))
+        .build();
+
+    let mut files = Vec::new();
+    let mut total_bytes: u64 = 0;
+    let mut skipped_binary = 0;
+    let mut limited = false;
+    let max_bytes = max_bytes();
+
+    for (visited, entry) in walk.filter_map(Result::ok).enumerate() {
+        if visited >= MAX_WALK_ENTRIES {
+            limited = true;
+            break;
+        }
+        if !entry.file_type().is_some_and(|kind| kind.is_file()) {
+            continue;
+        }
+        let path = entry.path();
+        let relative = path.strip_prefix(root).unwrap_or(path);
+        if matcher.is_some_and(|(m, _)| !m.is_match(relative)) {
+            continue;
+        }
+        if files.len() >= MAX_DIR_FILES {
+            limited = true;
+            break;
+        }
+        let size = entry.metadata().map(|meta| meta.len()).unwrap_or(0);
+        if total_bytes + size.min(max_bytes as u64) > MAX_DIR_BYTES {
+            limited = true;
+            break;
+        }
+        let Ok(text) = read_text(path, max_bytes) else {
+            skipped_binary += 1;
+            continue;
+        };
+        total_bytes += text.content.len() as u64;
+        files.push((relative.to_path_buf(), text));
+    }
+
+    let mut summary = format!("{} files, {} bytes", files.len(), total_bytes);
+    if skipped_binary > 0 {
+        summary.push_str(&format!(", {} binary skipped", skipped_binary));
+    }
+    if limited {
+        summary.push_str(&format!(
+            ", stopped at the limit ({} files / {} bytes)",
+            MAX_DIR_FILES, MAX_DIR_BYTES
+        ));
+    }
+
+    let tree = render_tree(files.iter().map(|(relative, _)| relative.as_path()));
+    let mut attachments = vec![Attachment {
+        path: format!("{}/ (tree)", display_root),
+        content: format!("{}\n{}", summary, tree),
+        range: None,
+        note: None,
+        image: None,
+    }];
+    attachments.extend(files.into_iter().map(|(relative, text)| Attachment {
+        path: format!("{}/{}", display_root, relative.display()),
+        note: text.note(),
+        content: text.content,
+        range: None,
+        image: None,
+    }));
+    attachments
+}
+
+/// Renders relative paths as an indented tree, directories first in path order.
+fn render_tree<'a>(paths: impl Iterator<Item = &'a Path>) -> String {
+    let mut entries = BTreeSet::new();
+    for path in paths {
+        let components: Vec<String> = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy().to_string())
+            .collect();
+        for depth in 1..components.len() {
+            entries.insert((components[..depth].to_vec(), true));
+        }
+        entries.insert((components, false));
+    }
+
+    entries
+        .into_iter()
+        .map(|(components, is_dir)| {
+            let indent = "  ".repeat(components.len() - 1);
+            let name = components.last().cloned().unwrap_or_default();
+            if is_dir {
+                format!("{}{}/", indent, name)
+            } else {
+                format!("{}{}", indent, name)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::attach::scratch;
+    use std::fs;
+
+    #[test]
+    fn splits_globs_at_the_first_pattern_part() {
+        assert_eq!(
+            split_glob("./src/**/*.rs"),
+            ("./src".into(), "**/*.rs".into())
+        );
+        assert_eq!(split_glob("/etc/*.conf"), ("/etc".into(), "*.conf".into()));
+        assert_eq!(split_glob("/*.json"), ("/".into(), "*.json".into()));
+        assert_eq!(split_glob("*.md"), (".".into(), "*.md".into()));
+        assert_eq!(split_glob("docs/**"), ("docs".into(), "**".into()));
+    }
+
+    #[test]
+    fn skips_ignored_and_binary_files() {
+        let dir = scratch::dir("dir-ignore");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join(".gitignore"), "ignored.txt\n").unwrap();
+        fs::write(dir.join("a.txt"), "alpha\n").unwrap();
+        fs::write(dir.join("ignored.txt"), "secret\n").unwrap();
+        fs::write(dir.join("data.bin"), [0u8, 1, 2, 0, 3]).unwrap();
+        fs::write(dir.join("sub/b.txt"), "beta\n").unwrap();
+
+        let attachments = attach_dir("proj/", dir.to_str().unwrap());
+        let labels: Vec<&str> = attachments.iter().map(|a| a.path.as_str()).collect();
+        assert_eq!(labels, ["proj/ (tree)", "proj/a.txt", "proj/sub/b.txt"]);
+        assert!(
+            attachments[0]
+                .content
+                .starts_with("2 files, 11 bytes, 1 binary skipped\n")
+        );
+        assert!(attachments[0].content.ends_with("a.txt\nsub/\n  b.txt"));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn globs_match_below_their_base() {
+        let dir = scratch::dir("dir-glob");
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.rs"), "fn a() {}\n").unwrap();
+        fs::write(dir.join("b.md"), "# b\n").unwrap();
+        fs::write(dir.join("sub/c.rs"), "fn c() {}\n").unwrap();
+
+        let root = dir.to_str().unwrap();
+        let labels = |pattern: &str| {
+            attach_glob(&format!("p/{}", pattern), &format!("{}/{}", root, pattern))
+                .unwrap()
+                .into_iter()
+                .skip(1)
+                .map(|a| a.path)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(labels("*.rs"), ["p/a.rs"]);
+        assert_eq!(labels("**/*.rs"), ["p/a.rs", "p/sub/c.rs"]);
+        assert!(attach_glob("x/*.rs", &format!("{}/missing/*.rs", root)).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stops_at_the_file_limit() {
+        let dir = scratch::dir("dir-limit");
+        for index in 0..=MAX_DIR_FILES {
+            fs::write(dir.join(format!("f{:03}.txt", index)), "x").unwrap();
+        }
+        let attachments = attach_dir("many/", dir.to_str().unwrap());
+        assert_eq!(attachments.len(), MAX_DIR_FILES + 1);
+        assert!(
+            attachments[0]
+                .content
+                .contains("stopped at the limit (200 files")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,172 @@
+use quick_xml::Reader;
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::{BytesStart, Event};
+use std::fs::{self, File};
+use std::io::Read;
+use std::panic;
+use std::path::Path;
+
+/// Document formats whose text is extracted instead of read as-is.
+#[derive(Clone, Copy)]
+enum Kind {
+    Pdf,
+    Docx,
+    Odt,
+}
+
+fn kind(path: &str) -> Option<Kind> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "pdf" => Some(Kind::Pdf),
+        "docx" => Some(Kind::Docx),
+        "odt" => Some(Kind::Odt),
+        _ => None,
+    }
+}
+
+/// Returns true for `.pdf`, `.docx` and `.odt` paths.
+pub fn is_document_path(path: &str) -> bool {
+    kind(path).is_some()
+}
+
+/// Extracts the text of each page. Word processor files only break pages
+/// where the file records it, so they often come back as a single page.
+pub fn extract_pages(path: &str, expanded: &Path) -> Result<Vec<String>, String> {
+    let pages = match kind(path) {
+        Some(Kind::Pdf) => {
+            let bytes = fs::read(expanded).map_err(|err| err.to_string())?;
+            // The PDF parser panics on some malformed files.
+            panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(&bytes))
+                .map_err(|_| "could not parse PDF".to_string())?
+                .map_err(|err| format!("could not parse PDF: {}", err))?
+        }
+        Some(Kind::Docx) => xml_pages(expanded, "word/document.xml", docx_piece)?,
+        Some(Kind::Odt) => xml_pages(expanded, "content.xml", odt_piece)?,
+        None => return Err("not a document".to_string()),
+    };
+    if pages.iter().all(|page| page.trim().is_empty()) {
+        return Err("no extractable text (scanned document?)".to_string());
+    }
+    Ok(pages)
+}
+
+/// Joins pages `start..=end` (1-based) with `[page N]` markers.
+pub fn render_pages(pages: &[String], start: usize, end: usize) -> String {
+    pages
+        .iter()
+        .enumerate()
+        .skip(start - 1)
+        .take(end + 1 - start)
+        .map(|(idx, page)| format!("[page {}]\n{}", idx + 1, page.trim()))
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// What a document XML element contributes to the extracted text.
+enum Piece {
+    Text(&'static str),
+    PageBreak,
+    Skip,
+}
+
+/// Maps a docx element to text; `start` is `None` for closing tags.
+fn docx_piece(name: &str, start: Option<&BytesStart>) -> Piece {
+    match (name, start) {
+        ("p", None) => Piece::Text("\n"),
+        ("tab", Some(_)) => Piece::Text("\t"),
+        ("br", Some(element)) => {
+            let page = element
+                .try_get_attribute("w:type")
+                .ok()
+                .flatten()
+                .is_some_and(|attr| attr.value.as_ref() == "page");
+            if page {
+                Piece::PageBreak
+            } else {
+                Piece::Text("\n")
+            }
+        }
+        ("lastRenderedPageBreak", Some(_)) => Piece::PageBreak,
+        _ => Piece::Skip,
+    }
+}
+
+/// Maps an odt element to text; `start` is `None` for closing tags.
+fn odt_piece(name: &str, start: Option<&BytesStart>) -> Piece {
+    match (name, start) {
+        ("p" | "h", None) => Piece::Text("\n"),
+        ("tab", Some(_)) => Piece::Text("\t"),
+        ("line-break", Some(_)) => Piece::Text("\n"),
+        ("s", Some(_)) => Piece::Text(" "),
+        ("soft-page-break", Some(_)) => Piece::PageBreak,
+        _ => Piece::Skip,
+    }
+}
+
+/// Elements whose text is not part of the visible document (field codes, deletions).
+const HIDDEN_ELEMENTS: [&str; 2] = ["instrText", "delText"];
+
+/// Reads `entry` from a zipped office document and splits its text into pages.
+fn xml_pages(
+    path: &Path,
+    entry: &str,
+    piece: fn(&str, Option<&BytesStart>) -> Piece,
+) -> Result<Vec<String>, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let mut archive = zip::ZipArchive::new(file).map_err(|err| err.to_string())?;
+    let mut xml = String::new();
+    archive
+        .by_name(entry)
+        .map_err(|err| format!("{}: {}", entry, err))?
+        .read_to_string(&mut xml)
+        .map_err(|err| err.to_string())?;
+
+    let mut reader = Reader::from_str(&xml);
+    let mut pages = vec![String::new()];
+    let mut hidden = false;
+    loop {
+        let next = match reader.read_event().map_err(|err| err.to_string())? {
+            Event::Start(element) => {
+                let name = element.local_name();
+                hidden |= HIDDEN_ELEMENTS.contains(&name.as_ref());
+                piece(name.as_ref(), Some(&element))
+            }
+            Event::Empty(element) => piece(element.local_name().as_ref(), Some(&element)),
+            Event::End(element) => {
+                let name = element.local_name();
+                if HIDDEN_ELEMENTS.contains(&name.as_ref()) {
+                    hidden = false;
+                }
+                piece(name.as_ref(), None)
+            }
+            Event::Text(text) if !hidden => {
+                push_text(&mut pages, &text.xml10_content());
+                continue;
+            }
+            Event::GeneralRef(entity) if !hidden => {
+                match entity.resolve_char_ref() {
+                    Ok(Some(ch)) => push_text(&mut pages, ch.encode_utf8(&mut [0; 4])),
+                    _ => push_text(&mut pages, resolve_predefined_entity(&entity).unwrap_or("")),
+                }
+                continue;
+            }
+            Event::Eof => break,
+            _ => continue,
+        };
+        match next {
+            Piece::Text(text) => push_text(&mut pages, text),
+            // Breaks at the very start of a page come from layout, not content.
+            Piece::PageBreak if pages.last().is_some_and(|page| !page.trim().is_empty()) => {
+                pages.push(String::new())
+            }
+            _ => {}
+        }
+    }
+    Ok(pages)
+}
+
+fn push_text(pages: &mut [String], text: &str) {
+    if let Some(page) = pages.last_mut() {
+        page.push_str(text);
+    }
+}
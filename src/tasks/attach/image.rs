@@ -0,0 +1,153 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::core;
+use crate::utils::format_size;
+
+/// Default maximum encoded size of one image.
+const DEFAULT_MAX_BYTES: usize = 4 * 1024 * 1024;
+/// Default maximum width or height, larger images are downscaled.
+const DEFAULT_MAX_SIDE: u32 = 2048;
+/// Smallest side images are downscaled to; lower limits are raised to it.
+const MIN_SIDE: u32 = 256;
+/// Largest image file read at all, before any downscaling.
+const MAX_FILE_BYTES: u64 = 64 * 1024 * 1024;
+/// JPEG quality used when re-encoding downscaled images.
+const JPEG_QUALITY: u8 = 85;
+
+/// Image file ready to be sent as a multimodal content part.
+pub struct ImageData {
+    pub mime: &'static str,
+    pub bytes: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ImageData {
+    /// Returns the image as a `data:` URL.
+    pub fn data_url(&self) -> String {
+        format!("data:{};base64,{}", self.mime, STANDARD.encode(&self.bytes))
+    }
+
+    /// Placeholder shown in the text prompt where the image is attached.
+    pub fn describe(&self) -> String {
+        format!(
+            "[image {} {}x{}, {}, sent as image content]",
+            self.mime,
+            self.width,
+            self.height,
+            format_size(self.bytes.len() as u64)
+        )
+    }
+
+    pub fn to_core(&self, label: &str) -> core::Image {
+        core::Image {
+            label: label.to_string(),
+            data_url: self.data_url(),
+        }
+    }
+}
+
+/// Returns true for paths with a supported image extension.
+pub fn is_image_path(path: &str) -> bool {
+    image_format(path).is_some()
+}
+
+fn image_format(path: &str) -> Option<ImageFormat> {
+    let ext = Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+    match ext.as_str() {
+        "png" => Some(ImageFormat::Png),
+        "jpg" | "jpeg" => Some(ImageFormat::Jpeg),
+        "webp" => Some(ImageFormat::WebP),
+        "gif" => Some(ImageFormat::Gif),
+        _ => None,
+    }
+}
+
+/// Reads an image, downscaling it past `NETERO_IMAGE_MAX_SIDE` pixels and
+/// re-encoding it when it exceeds `NETERO_IMAGE_MAX_BYTES`.
+pub fn read_image(path: &Path) -> Result<(ImageData, Option<String>), String> {
+    let max_bytes = core::env_number("NETERO_IMAGE_MAX_BYTES").unwrap_or(DEFAULT_MAX_BYTES);
+    let max_side = core::env_number("NETERO_IMAGE_MAX_SIDE")
+        .map_or(DEFAULT_MAX_SIDE, |side| side.min(u32::MAX as usize) as u32)
+        .max(MIN_SIDE);
+
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let size = file.metadata().map_err(|err| err.to_string())?.len();
+    let too_large = |size: u64| {
+        format!(
+            "image file is too large ({}, limit {})",
+            format_size(size),
+            format_size(MAX_FILE_BYTES)
+        )
+    };
+    if size > MAX_FILE_BYTES {
+        return Err(too_large(size));
+    }
+    let mut bytes = Vec::with_capacity(size as usize);
+    file.take(MAX_FILE_BYTES + 1)
+        .read_to_end(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    if bytes.len() as u64 > MAX_FILE_BYTES {
+        return Err(too_large(bytes.len() as u64));
+    }
+    let format = image::guess_format(&bytes).map_err(|_| "not a supported image".to_string())?;
+    let mime = match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::WebP => "image/webp",
+        ImageFormat::Gif => "image/gif",
+        _ => return Err("unsupported image format".to_string()),
+    };
+    let decoded = image::load_from_memory_with_format(&bytes, format)
+        .map_err(|err| format!("invalid image: {}", err))?;
+    let (width, height) = decoded.dimensions();
+
+    if width <= max_side && height <= max_side && bytes.len() <= max_bytes {
+        let image = ImageData {
+            mime,
+            bytes,
+            width,
+            height,
+        };
+        return Ok((image, None));
+    }
+
+    // Shrink until the re-encoded JPEG fits, giving up below a usable size.
+    let original = format_size(bytes.len() as u64);
+    let mut side = max_side.min(width.max(height));
+    while side >= MIN_SIDE {
+        let image = encode_jpeg(&decoded, side)?;
+        if image.bytes.len() <= max_bytes {
+            let note = format!("downscaled from {}x{} ({})", width, height, original);
+            return Ok((image, Some(note)));
+        }
+        side = side * 3 / 4;
+    }
+    Err(format!(
+        "image is too large ({}, limit {})",
+        original,
+        format_size(max_bytes as u64)
+    ))
+}
+
+fn encode_jpeg(decoded: &DynamicImage, side: u32) -> Result<ImageData, String> {
+    // JPEG has no alpha channel.
+    let resized = decoded.resize(side, side, FilterType::Triangle).to_rgb8();
+    let mut bytes = Vec::new();
+    JpegEncoder::new_with_quality(&mut bytes, JPEG_QUALITY)
+        .encode_image(&resized)
+        .map_err(|err| err.to_string())?;
+    Ok(ImageData {
+        mime: "image/jpeg",
+        bytes,
+        width: resized.width(),
+        height: resized.height(),
+    })
+}
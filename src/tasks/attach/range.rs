@@ -0,0 +1,242 @@
+use regex::Regex;
+
+/// Lines shown before and after a single-line reference like `./file.rs:120`.
+const CONTEXT_LINES: usize = 15;
+/// Upper bound on lines taken for a symbol whose end cannot be found.
+const MAX_SYMBOL_LINES: usize = 400;
+
+/// Part of a file requested after the path.
+pub enum Selector {
+    /// `path:N-M`, inclusive 1-based.
+    Lines(usize, usize),
+    /// `path:N`, shown with surrounding context.
+    Line(usize),
+    /// `path#name`, best-effort symbol lookup.
+    Symbol(String),
+}
+
+/// Parses `N` or `N-M` into an inclusive 1-based range.
+pub fn parse_line_range(value: &str) -> Option<(usize, usize)> {
+    let (start, end) = match value.split_once('-') {
+        Some((start, end)) => (start.parse().ok()?, end.parse().ok()?),
+        None => {
+            let line = value.parse().ok()?;
+            (line, line)
+        }
+    };
+    if start == 0 || end < start {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Splits `path:N-M`, `path:N` or `path#symbol` into the path and its selector.
+pub fn split_selector(token: &str) -> (&str, Option<Selector>) {
+    if let Some((path, symbol)) = token.rsplit_once('#')
+        && !path.is_empty()
+        && !symbol.is_empty()
+        && symbol
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || ch == '_' || ch == ':' || ch == '.')
+    {
+        return (path, Some(Selector::Symbol(symbol.to_string())));
+    }
+    if let Some((path, range)) = token.rsplit_once(':')
+        && let Some((start, end)) = parse_line_range(range)
+    {
+        let selector = if range.contains('-') {
+            Selector::Lines(start, end)
+        } else {
+            Selector::Line(start)
+        };
+        return (path, Some(selector));
+    }
+    (token, None)
+}
+
+/// Returns the selected lines and the inclusive 1-based range actually taken.
+pub fn select(
+    content: &str,
+    path: &str,
+    selector: &Selector,
+) -> Result<(String, (usize, usize)), String> {
+    let lines: Vec<&str> = content.lines().collect();
+    let total = lines.len();
+    // Checked before adding context, which would pull the start back in range.
+    if let Selector::Lines(line, _) | Selector::Line(line) = selector
+        && *line > total
+    {
+        return Err(format!(
+            "line {} is past the end of the file ({} lines)",
+            line, total
+        ));
+    }
+    let (start, end) = match selector {
+        Selector::Lines(start, end) => (*start, (*end).min(total)),
+        Selector::Line(line) => (
+            line.saturating_sub(CONTEXT_LINES).max(1),
+            (line + CONTEXT_LINES).min(total),
+        ),
+        Selector::Symbol(name) => {
+            find_symbol(&lines, path, name).ok_or_else(|| format!("symbol '{}' not found", name))?
+        }
+    };
+    Ok((lines[start - 1..end].join("\n"), (start, end)))
+}
+
+/// Finds the line range of a symbol definition using per-language patterns.
+fn find_symbol(lines: &[&str], path: &str, name: &str) -> Option<(usize, usize)> {
+    let name = regex::escape(name.rsplit("::").next().unwrap_or(name));
+    let ext = path.rsplit_once('.').map(|(_, ext)| ext).unwrap_or("");
+    let pattern = match ext {
+        "rs" => format!(
+            r"^\s*(pub(\([^)]*\))?\s+)?((async|const|unsafe|extern\s+\S+)\s+)*(fn|struct|enum|trait|mod|type|union|macro_rules!)\s*{name}\b|^\s*impl(<[^>]*>)?\s+([\w:]+\s+for\s+)?{name}\b"
+        ),
+        "py" => format!(r"^\s*(async\s+)?(def|class)\s+{name}\b"),
+        "go" => format!(r"^\s*(func(\s*\([^)]*\))?|type)\s+{name}\b"),
+        "js" | "jsx" | "ts" | "tsx" | "mjs" | "cjs" => format!(
+            r"^\s*(export\s+)?(default\s+)?(async\s+)?(function\*?|class|interface|type|enum)\s+{name}\b|^\s*(export\s+)?(const|let|var)\s+{name}\s*=|^\s*(async\s+)?{name}\s*\([^)]*\)\s*\{{"
+        ),
+        "rb" => format!(r"^\s*(def|class|module)\s+(self\.)?{name}\b"),
+        "sh" | "bash" | "zsh" => format!(r"^\s*(function\s+)?{name}\s*(\(\))?\s*\{{"),
+        _ => format!(
+            r"^\s*([\w:<>,\*&]+\s+)*\**{name}\s*\([^;]*$|^\s*(class|struct|enum|interface|def|fn|func|function)\s+{name}\b"
+        ),
+    };
+    let regex = Regex::new(&pattern).ok()?;
+    let start = lines.iter().position(|line| regex.is_match(line))?;
+
+    let end = if matches!(ext, "py" | "rb") {
+        indented_block_end(lines, start)
+    } else {
+        brace_block_end(lines, start)
+    };
+    // Include doc comments and attributes directly above the definition.
+    let mut first = start;
+    while first > 0 {
+        let above = lines[first - 1].trim_start();
+        if above.starts_with("//")
+            || above.starts_with("#[")
+            || above.starts_with('@')
+            || (above.starts_with('#') && ext == "py")
+        {
+            first -= 1;
+        } else {
+            break;
+        }
+    }
+    Some((first + 1, end + 1))
+}
+
+/// Returns the index of the line closing the brace block opened at or after `start`.
+fn brace_block_end(lines: &[&str], start: usize) -> usize {
+    let mut depth = 0i32;
+    let mut opened = false;
+    let last = (start + MAX_SYMBOL_LINES).min(lines.len()) - 1;
+    for (idx, line) in lines.iter().enumerate().take(last + 1).skip(start) {
+        for ch in line.chars() {
+            match ch {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        // A declaration ending in `;` before any brace (e.g. `struct Unit;`) is one line.
+        if (opened && depth <= 0) || (!opened && line.trim_end().ends_with(';')) {
+            return idx;
+        }
+    }
+    last
+}
+
+/// Returns the last line of an indentation-delimited block starting at `start`.
+fn indented_block_end(lines: &[&str], start: usize) -> usize {
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let base = indent(lines[start]);
+    let last = (start + MAX_SYMBOL_LINES).min(lines.len()) - 1;
+    let mut end = start;
+    for (idx, line) in lines.iter().enumerate().take(last + 1).skip(start + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if indent(line) <= base {
+            break;
+        }
+        end = idx;
+    }
+    end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbered(count: usize) -> String {
+        (1..=count)
+            .map(|line| format!("line {}", line))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn splits_ranges_lines_and_symbols() {
+        assert!(matches!(
+            split_selector("./a.rs:10-20"),
+            ("./a.rs", Some(Selector::Lines(10, 20)))
+        ));
+        assert!(matches!(
+            split_selector("./a.rs:7"),
+            ("./a.rs", Some(Selector::Line(7)))
+        ));
+        assert!(matches!(
+            split_selector("./a.rs#Parser::parse"),
+            ("./a.rs", Some(Selector::Symbol(name))) if name == "Parser::parse"
+        ));
+        assert!(matches!(
+            split_selector("./a.rs:20-10"),
+            ("./a.rs:20-10", None)
+        ));
+        assert!(matches!(split_selector("./a.rs:0"), ("./a.rs:0", None)));
+        assert!(matches!(split_selector("#tag"), ("#tag", None)));
+    }
+
+    #[test]
+    fn single_lines_get_context_clamped_to_the_file() {
+        let content = numbered(100);
+        let (text, range) = select(&content, "f.txt", &Selector::Line(3)).unwrap();
+        assert_eq!(range, (1, 18));
+        assert!(text.starts_with("line 1\n"));
+        let (_, range) = select(&content, "f.txt", &Selector::Line(95)).unwrap();
+        assert_eq!(range, (80, 100));
+        let (_, range) = select(&content, "f.txt", &Selector::Lines(90, 120)).unwrap();
+        assert_eq!(range, (90, 100));
+    }
+
+    #[test]
+    fn lines_past_the_end_are_an_error() {
+        let content = numbered(100);
+        assert_eq!(
+            select(&content, "f.txt", &Selector::Line(110)).unwrap_err(),
+            "line 110 is past the end of the file (100 lines)"
+        );
+        assert!(select(&content, "f.txt", &Selector::Lines(101, 105)).is_err());
+    }
+
+    #[test]
+    fn finds_symbols_with_their_docs() {
+        let rust = "use x;\n\n/// Adds.\n#[inline]\npub fn add(a: i32) -> i32 {\n    a + 1\n}\n\nfn other() {}";
+        let lines: Vec<&str> = rust.lines().collect();
+        assert_eq!(find_symbol(&lines, "m.rs", "add"), Some((3, 7)));
+        assert_eq!(find_symbol(&lines, "m.rs", "crate::other"), Some((9, 9)));
+        assert_eq!(find_symbol(&lines, "m.rs", "missing"), None);
+
+        let python =
+            "class A:\n    def run(self):\n        return 1\n\n    def stop(self):\n        pass\n";
+        let lines: Vec<&str> = python.lines().collect();
+        assert_eq!(find_symbol(&lines, "m.py", "run"), Some((2, 3)));
+        assert_eq!(find_symbol(&lines, "m.py", "A"), Some((1, 6)));
+    }
+}
@@ -0,0 +1,333 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::core;
+
+/// Default maximum bytes read from a single attached file.
+const DEFAULT_MAX_BYTES: usize = 256 * 1024;
+/// Bytes inspected to detect binary content and the text encoding.
+const SNIFF_BYTES: usize = 8192;
+
+/// Text encoding detected for an attached file.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Latin1,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Latin1 => "Latin-1",
+        }
+    }
+}
+
+/// Decoded content of an attached file.
+pub struct TextFile {
+    pub content: String,
+    /// Size of the file on disk.
+    pub size: u64,
+    pub truncated: bool,
+    pub encoding: Encoding,
+}
+
+impl TextFile {
+    /// Short note for the attachment summary, if anything unusual happened.
+    pub fn note(&self) -> Option<String> {
+        let mut notes = Vec::new();
+        if self.truncated {
+            notes.push(format!(
+                "truncated, {} total",
+                crate::utils::format_size(self.size)
+            ));
+        }
+        if self.encoding != Encoding::Utf8 {
+            notes.push(format!("decoded from {}", self.encoding.name()));
+        }
+        (!notes.is_empty()).then(|| notes.join(", "))
+    }
+}
+
+/// Maximum bytes read from one file, configurable with `NETERO_ATTACH_MAX_BYTES`.
+pub fn max_bytes() -> usize {
+    core::env_number("NETERO_ATTACH_MAX_BYTES").unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+/// Reads a text file, keeping the head and tail when it exceeds `max_bytes`.
+/// Binary files are rejected; non-UTF-8 text is decoded lossily.
+pub fn read_text(path: &Path, max_bytes: usize) -> Result<TextFile, String> {
+    let mut file = File::open(path).map_err(|err| err.to_string())?;
+    let size = file.metadata().map_err(|err| err.to_string())?.len();
+
+    let mut sniff = vec![0u8; SNIFF_BYTES.min(size as usize)];
+    file.read_exact(&mut sniff).map_err(|err| err.to_string())?;
+    let encoding = detect_encoding(&sniff).ok_or_else(|| "binary file skipped".to_string())?;
+    file.rewind().map_err(|err| err.to_string())?;
+
+    if size <= max_bytes as u64 {
+        // The size on disk is zero or stale for special and growing files, so
+        // never read more than the limit whatever it says.
+        let mut bytes = Vec::with_capacity(size as usize);
+        file.by_ref()
+            .take(max_bytes as u64 + 1)
+            .read_to_end(&mut bytes)
+            .map_err(|err| err.to_string())?;
+        if bytes.len() > max_bytes {
+            return Ok(cut_after(bytes, max_bytes, encoding));
+        }
+        let (content, encoding) = decode(&bytes, encoding);
+        return Ok(TextFile {
+            content,
+            size,
+            truncated: false,
+            encoding,
+        });
+    }
+
+    let half = (max_bytes / 2) as u64;
+    let mut head = vec![0u8; half as usize];
+    file.read_exact(&mut head).map_err(|err| err.to_string())?;
+    let mut tail_start = size - half;
+    if matches!(encoding, Encoding::Utf16Le | Encoding::Utf16Be) {
+        // Stays within the file when the limit leaves no tail at all.
+        tail_start = (tail_start + tail_start % 2).min(size);
+    }
+    file.seek(SeekFrom::Start(tail_start))
+        .map_err(|err| err.to_string())?;
+    let mut tail = Vec::new();
+    file.take(size - tail_start)
+        .read_to_end(&mut tail)
+        .map_err(|err| err.to_string())?;
+
+    if encoding == Encoding::Utf8 {
+        trim_partial_utf8(&mut head, &mut tail);
+    }
+    let (head, encoding) = decode(&head, encoding);
+    let (tail, _) = decode(&tail, encoding);
+    // Keep whole lines on both sides of the cut.
+    let head = head
+        .rsplit_once('\n')
+        .map_or(head.as_str(), |(kept, _)| kept);
+    let tail = tail
+        .split_once('\n')
+        .map_or(tail.as_str(), |(_, kept)| kept);
+    Ok(TextFile {
+        content: format!(
+            "{}\n[... truncated, {} bytes total ...]\n{}",
+            head, size, tail
+        ),
+        size,
+        truncated: true,
+        encoding,
+    })
+}
+
+/// Keeps the first `max_bytes` of a stream that turned out longer than its
+/// size on disk; the rest was never read, so there is no tail.
+fn cut_after(mut bytes: Vec<u8>, max_bytes: usize, encoding: Encoding) -> TextFile {
+    let size = bytes.len() as u64;
+    bytes.truncate(max_bytes);
+    if encoding == Encoding::Utf8 {
+        trim_partial_utf8(&mut bytes, &mut Vec::new());
+    }
+    let (head, encoding) = decode(&bytes, encoding);
+    let head = head
+        .rsplit_once('\n')
+        .map_or(head.as_str(), |(kept, _)| kept);
+    TextFile {
+        content: format!("{}\n[... truncated after {} bytes ...]", head, max_bytes),
+        size,
+        truncated: true,
+        encoding,
+    }
+}
+
+/// Keeps the head and tail of already decoded text longer than `max_bytes`.
+pub fn truncate_text(content: String, max_bytes: usize) -> (String, bool) {
+    if content.len() <= max_bytes {
+        return (content, false);
+    }
+    let mut head_end = max_bytes / 2;
+    while !content.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    let mut tail_start = content.len() - max_bytes / 2;
+    while !content.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    let head = &content[..head_end];
+    let tail = &content[tail_start..];
+    let head = head.rsplit_once('\n').map_or(head, |(kept, _)| kept);
+    let tail = tail.split_once('\n').map_or(tail, |(_, kept)| kept);
+    let truncated = format!(
+        "{}\n[... truncated, {} bytes total ...]\n{}",
+        head,
+        content.len(),
+        tail
+    );
+    (truncated, true)
+}
+
+/// Guesses the encoding from a prefix, returning `None` for binary data.
+fn detect_encoding(sniff: &[u8]) -> Option<Encoding> {
+    if sniff.starts_with(&[0xFF, 0xFE]) {
+        return Some(Encoding::Utf16Le);
+    }
+    if sniff.starts_with(&[0xFE, 0xFF]) {
+        return Some(Encoding::Utf16Be);
+    }
+    // UTF-16 without a BOM: ASCII text leaves every other byte zero.
+    if sniff.len() >= 4 {
+        let pairs = sniff.len() / 2;
+        let even_zero = sniff.iter().step_by(2).filter(|b| **b == 0).count();
+        let odd_zero = sniff.iter().skip(1).step_by(2).filter(|b| **b == 0).count();
+        if odd_zero * 10 >= pairs * 8 && even_zero * 10 <= pairs {
+            return Some(Encoding::Utf16Le);
+        }
+        if even_zero * 10 >= pairs * 8 && odd_zero * 10 <= pairs {
+            return Some(Encoding::Utf16Be);
+        }
+    }
+    if sniff.contains(&0) {
+        return None;
+    }
+    let control = sniff
+        .iter()
+        .filter(|b| **b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0C | 0x1B))
+        .count();
+    if control * 10 > sniff.len() {
+        return None;
+    }
+    match std::str::from_utf8(sniff) {
+        Ok(_) => Some(Encoding::Utf8),
+        // A multibyte character cut by the end of the sniffed prefix.
+        Err(err) if err.error_len().is_none() => Some(Encoding::Utf8),
+        Err(_) => Some(Encoding::Latin1),
+    }
+}
+
+/// Decodes bytes, falling back to Latin-1 when UTF-8 turns out to be invalid.
+fn decode(bytes: &[u8], encoding: Encoding) -> (String, Encoding) {
+    match encoding {
+        Encoding::Utf8 => {
+            let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+            match std::str::from_utf8(bytes) {
+                Ok(text) => (text.to_string(), Encoding::Utf8),
+                Err(_) => decode(bytes, Encoding::Latin1),
+            }
+        }
+        Encoding::Latin1 => (
+            bytes.iter().map(|b| char::from(*b)).collect(),
+            Encoding::Latin1,
+        ),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|pair| {
+                    if encoding == Encoding::Utf16Le {
+                        u16::from_le_bytes([pair[0], pair[1]])
+                    } else {
+                        u16::from_be_bytes([pair[0], pair[1]])
+                    }
+                })
+                .collect();
+            let text = String::from_utf16_lossy(&units);
+            (text.trim_start_matches('\u{FEFF}').to_string(), encoding)
+        }
+    }
+}
+
+/// Drops UTF-8 sequences split by the head/tail cut.
+fn trim_partial_utf8(head: &mut Vec<u8>, tail: &mut Vec<u8>) {
+    if let Err(err) = std::str::from_utf8(head)
+        && err.error_len().is_none()
+    {
+        head.truncate(err.valid_up_to());
+    }
+    let skip = tail.iter().take_while(|b| (**b & 0xC0) == 0x80).count();
+    tail.drain(..skip);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::attach::scratch;
+    use std::fs;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn detects_encodings_and_binary_data() {
+        assert!(detect_encoding("plain text\n".as_bytes()) == Some(Encoding::Utf8));
+        assert!(detect_encoding("café".as_bytes()) == Some(Encoding::Utf8));
+        assert!(detect_encoding(b"caf\xe9 cr\xe8me") == Some(Encoding::Latin1));
+        assert!(detect_encoding(&[0xFF, 0xFE, b'a', 0]) == Some(Encoding::Utf16Le));
+        assert!(detect_encoding(&[0xFE, 0xFF, 0, b'a']) == Some(Encoding::Utf16Be));
+        assert!(detect_encoding(&utf16le("no byte order mark")) == Some(Encoding::Utf16Le));
+        assert!(detect_encoding(&[0x7F, b'E', b'L', b'F', 0, 0, 1, 2, 0, 9]).is_none());
+    }
+
+    #[test]
+    fn truncates_decoded_text_on_line_and_char_boundaries() {
+        let (short, truncated) = truncate_text("short".to_string(), 10);
+        assert_eq!((short.as_str(), truncated), ("short", false));
+
+        let text = "first line\nmiddle ééééé\nlast line".to_string();
+        let (cut, truncated) = truncate_text(text, 30);
+        assert!(truncated);
+        assert_eq!(
+            cut,
+            "first line\n[... truncated, 38 bytes total ...]\nlast line"
+        );
+
+        // Cuts inside `é` move outwards to whole characters.
+        let (cut, _) = truncate_text("éééé".to_string(), 5);
+        assert_eq!(cut, "é\n[... truncated, 8 bytes total ...]\né");
+    }
+
+    #[test]
+    fn reads_the_head_and_tail_of_long_files() {
+        let dir = scratch::dir("read-long");
+        let path = dir.join("long.txt");
+        let lines: Vec<String> = (1..=100).map(|line| format!("line {}", line)).collect();
+        fs::write(&path, lines.join("\n")).unwrap();
+
+        let text = read_text(&path, 64).unwrap();
+        assert!(text.truncated);
+        assert!(text.content.starts_with("line 1\n"));
+        assert!(text.content.ends_with("\nline 100"));
+        assert!(
+            text.content
+                .contains("[... truncated, 791 bytes total ...]")
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn tiny_limits_on_odd_sized_utf16_files() {
+        let dir = scratch::dir("read-utf16");
+        let path = dir.join("odd.txt");
+        let mut bytes = vec![0xFF, 0xFE];
+        bytes.extend(utf16le("hello"));
+        bytes.push(b'!');
+        fs::write(&path, &bytes).unwrap();
+
+        for max_bytes in [1, 2, 3] {
+            let text = read_text(&path, max_bytes).unwrap();
+            assert!(text.truncated);
+            assert!(text.encoding == Encoding::Utf16Le);
+        }
+        let text = read_text(&path, 64).unwrap();
+        assert_eq!(text.content, "hello");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,74 +1,110 @@
 use crate::core;
+use crate::tasks::attach;
+use crate::tasks::extract;
+use crate::tasks::inline::{InlineCommand, run_commands, strip_inline_commands};
 use crate::tasks::render;
+use crate::tasks::template::{self, Template};
 use crate::utils;
-use rustyline::Context;
-use rustyline::Helper;
 use rustyline::completion::{Completer, FilenameCompleter, Pair};
 use rustyline::error::ReadlineError;
-use rustyline::highlight::Highlighter;
-use rustyline::hint::Hinter;
-use rustyline::validate::Validator;
+use rustyline::highlight::{CmdKind, Highlighter};
+use rustyline::hint::{Hinter, HistoryHinter};
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper};
+use std::borrow::Cow;
 use std::fs;
 use std::io::Write;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
 
+use super::complete::ShellCandidates;
 use super::eval::{eval_expr, format_eval_error};
+use super::export;
+use super::files::SessionFiles;
+use super::highlight::highlight_input;
+use super::history::Resend;
+use super::input::{
+    confirm_command, confirm_yes_no, edit_in_editor, edit_line, needs_continuation,
+};
 use super::lang::{lang_display_name, normalize_lang_tag};
-use super::parse::{split_args, strip_inline_commands};
-
-const HELP_TEXT: &str = "\nCommands:\n\
-/help  Show this help message\n\
-/clean Clear chat history\n\
-/add   Attach file contents to chat context\n\
-/trans Translate text (uses LLM)\n\
-/eval  Evaluate arithmetic expression\n\
-/save  Save an informe about the chat\n\
-/stream [on|off] Toggle streaming output\n";
+use super::parse::split_args;
+use super::persona::{Persona, list_personas};
+use super::registry::{Completion, Registry};
+use super::task::Chat;
 
 /// Provides command name completions for slash-prefixed commands in the prompt.
 pub struct CommandCompleter {
-    /// The set of slash commands available for completion.
-    commands: Vec<&'static str>,
+    /// Slash commands with their argument completions and usage hints.
+    registry: Rc<Registry>,
+    /// Names and aliases of every slash command.
+    commands: Vec<String>,
     /// Filename completer for /add paths.
     file_completer: FilenameCompleter,
-    /// Common command names for #!(...) completion.
-    inline_commands: Vec<&'static str>,
-    /// Common git subcommands for #!(git ...) completion.
-    git_subcommands: Vec<&'static str>,
+    /// Executables, git subcommands and refs for #!(...) completion.
+    shell: ShellCandidates,
+    /// Suggests the rest of a previous message as it is typed.
+    history_hinter: HistoryHinter,
+    /// Line being edited when Ctrl-X Ctrl-E asked to open `$EDITOR`.
+    pub compose_request: Arc<Mutex<Option<String>>>,
 }
 
 impl CommandCompleter {
-    pub fn new(commands: Vec<&'static str>) -> Self {
+    pub fn new(registry: &Rc<Registry>) -> Self {
         Self {
-            commands,
+            registry: registry.clone(),
+            commands: registry.names(),
             file_completer: FilenameCompleter::new(),
-            inline_commands: vec![
-                "ls", "cat", "rg", "git", "pwd", "grep", "sed", "awk", "head", "tail",
-            ],
-            git_subcommands: vec![
-                "status", "add", "commit", "push", "pull", "fetch", "log", "diff", "show",
-                "branch", "checkout", "switch", "merge", "rebase", "stash", "reset", "restore",
-            ],
+            shell: ShellCandidates::new(),
+            history_hinter: HistoryHinter::new(),
+            compose_request: Arc::new(Mutex::new(None)),
         }
     }
 }
 
 /// Enables rustyline helper integration for slash command completion.
 impl Helper for CommandCompleter {}
-/// Disables hints while still fulfilling the rustyline helper contract.
+/// Shows the arguments of the slash command being typed, otherwise the rest
+/// of the newest matching history entry (accepted with Right or End).
 impl Hinter for CommandCompleter {
     type Hint = String;
 
-    /// Returns no hint so user input remains unobstructed.
-    fn hint(&self, _line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> {
-        None
+    fn hint(&self, line: &str, pos: usize, ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        self.registry
+            .hint(line)
+            .or_else(|| self.history_hinter.hint(line, pos, ctx))
     }
 }
 
-/// Disables highlighting while still fulfilling the rustyline helper contract.
-impl Highlighter for CommandCompleter {}
+/// Colors slash commands, inline directives and attachment paths.
+impl Highlighter for CommandCompleter {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        highlight_input(line, &self.commands)
+    }
+
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(format!("\x1b[2m{}\x1b[22m", hint))
+    }
 
-/// Disables validation while still fulfilling the rustyline helper contract.
-impl Validator for CommandCompleter {}
+    /// Paths can appear or disappear anywhere in the line, so every edit
+    /// redraws it.
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        true
+    }
+}
+
+/// Keeps reading lines while the input ends with `\` or has an open code fence.
+impl Validator for CommandCompleter {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if needs_continuation(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
 
 /// Implements slash command completion for rustyline.
 impl Completer for CommandCompleter {
@@ -81,56 +117,88 @@ impl Completer for CommandCompleter {
         pos: usize,
         ctx: &Context<'_>,
     ) -> Result<(usize, Vec<Pair>), ReadlineError> {
-        if line.starts_with("/add ") && pos >= 5 {
-            return self.file_completer.complete(line, pos, ctx);
-        }
         let start = line[..pos]
             .rfind(|c: char| c.is_whitespace())
             .map(|idx| idx + 1)
             .unwrap_or(0);
         let token = &line[start..pos];
-        if let Some(inline_start) = find_inline_start(&line[..pos]) {
-            if is_inside_inline(&line[..pos], inline_start) {
-                let inline_slice = &line[inline_start..pos];
-                let token_start = inline_slice
-                    .rfind(|c: char| c.is_whitespace())
-                    .map(|idx| inline_start + idx + 1)
-                    .unwrap_or(inline_start);
-                let inline_token = &line[token_start..pos];
-                if inline_token.starts_with("./")
-                    || inline_token.starts_with("../")
-                    || inline_token.starts_with('/')
-                    || inline_token.starts_with("~/")
-                {
-                    return self.file_completer.complete(line, pos, ctx);
-                }
-                if token_start == inline_start {
-                    let matches = self
-                        .inline_commands
+        // Arguments of a slash command complete as the registry says.
+        if let Some(name_end) = line.find(char::is_whitespace)
+            && pos > name_end
+        {
+            let values = match self.registry.completion(&line[..name_end]) {
+                Some(Completion::Paths) => return self.file_completer.complete(line, pos, ctx),
+                // `/help trans` and `/help /trans` both work.
+                Some(Completion::Commands) => Some(
+                    self.commands
                         .iter()
-                        .filter(|cmd| cmd.starts_with(inline_token))
-                        .map(|cmd| Pair {
-                            display: cmd.to_string(),
-                            replacement: cmd.to_string(),
+                        .map(|name| {
+                            if token.starts_with('/') {
+                                name.clone()
+                            } else {
+                                name.trim_start_matches('/').to_string()
+                            }
                         })
-                        .collect();
-                    return Ok((token_start, matches));
-                }
-                if let Some((first, first_end)) = first_inline_token(line, inline_start, pos) {
-                    if first == "git" && token_start > first_end {
-                        let matches = self
-                            .git_subcommands
-                            .iter()
-                            .filter(|cmd| cmd.starts_with(inline_token))
-                            .map(|cmd| Pair {
-                                display: cmd.to_string(),
-                                replacement: cmd.to_string(),
-                            })
-                            .collect();
-                        return Ok((token_start, matches));
+                        .collect(),
+                ),
+                Some(Completion::Values(values)) => Some(values()),
+                _ => None,
+            };
+            if let Some(values) = values {
+                let matches = values
+                    .into_iter()
+                    .filter(|value| value.starts_with(token))
+                    .map(|value| Pair {
+                        display: value.clone(),
+                        replacement: value,
+                    })
+                    .collect();
+                return Ok((start, matches));
+            }
+        }
+        if let Some(inline_start) = find_inline_start(&line[..pos])
+            && is_inside_inline(&line[..pos], inline_start)
+        {
+            let inline_slice = &line[inline_start..pos];
+            let token_start = inline_slice
+                .rfind(|c: char| c.is_whitespace())
+                .map(|idx| inline_start + idx + 1)
+                .unwrap_or(inline_start);
+            let inline_token = &line[token_start..pos];
+            if inline_token.starts_with("./")
+                || inline_token.starts_with("../")
+                || inline_token.starts_with('/')
+                || inline_token.starts_with("~/")
+            {
+                return self.file_completer.complete(line, pos, ctx);
+            }
+            // Only the words of the command the cursor is in, after the
+            // last `|`, `;`, `&&` or `||`.
+            let words: Vec<&str> = line[inline_start..token_start].split_whitespace().collect();
+            let command = words
+                .rsplit(|word| is_separator(word))
+                .next()
+                .unwrap_or_default();
+            return match command {
+                [] => Ok((
+                    token_start,
+                    candidates(self.shell.executables(), inline_token),
+                )),
+                ["git"] => Ok((
+                    token_start,
+                    candidates(self.shell.git_subcommands(), inline_token),
+                )),
+                ["git", ..] => {
+                    // Refs come first, then the files matching the same prefix.
+                    let mut matches = candidates(self.shell.git_refs(), inline_token);
+                    let (file_start, files) = self.file_completer.complete(line, pos, ctx)?;
+                    if file_start == token_start {
+                        matches.extend(files);
                     }
+                    Ok((token_start, matches))
                 }
-            }
+                _ => self.file_completer.complete(line, pos, ctx),
+            };
         }
         if start == 0 && token.starts_with('/') {
             let matches: Vec<Pair> = self
@@ -170,13 +238,27 @@ impl Completer for CommandCompleter {
 }
 
 fn find_inline_start(input: &str) -> Option<usize> {
-    input.rfind("#!(").map(|idx| idx + 3)
+    let plain = input.rfind("#!(").map(|idx| idx + 3);
+    let sandboxed = input.rfind("#!ro(").map(|idx| idx + 5);
+    plain.max(sandboxed)
 }
 
 fn is_inside_inline(input: &str, start: usize) -> bool {
     let mut depth = 1;
+    let mut quote: Option<char> = None;
+    let mut escape = false;
     for ch in input[start..].chars() {
-        if ch == '(' {
+        if escape {
+            escape = false;
+        } else if ch == '\\' && quote != Some('\'') {
+            escape = true;
+        } else if let Some(q) = quote {
+            if ch == q {
+                quote = None;
+            }
+        } else if ch == '"' || ch == '\'' {
+            quote = Some(ch);
+        } else if ch == '(' {
             depth += 1;
         } else if ch == ')' {
             depth -= 1;
@@ -188,40 +270,166 @@ fn is_inside_inline(input: &str, start: usize) -> bool {
     true
 }
 
-fn first_inline_token<'a>(input: &'a str, start: usize, end: usize) -> Option<(&'a str, usize)> {
-    let slice = &input[start..end];
-    let trimmed = slice.trim_start();
-    let leading_ws = slice.len() - trimmed.len();
-    let token_start = start + leading_ws;
-    let mut iter = trimmed.split_whitespace();
-    let first = iter.next()?;
-    let first_end = token_start + first.len();
-    Some((first, first_end))
+/// True for words that end one shell command and start another.
+fn is_separator(word: &str) -> bool {
+    matches!(word, "|" | "||" | "&&" | ";") || word.ends_with(';') || word.ends_with('|')
 }
 
-pub fn handle_help(user_input: &str) -> bool {
-    if user_input == "/help" {
-        println!("{HELP_TEXT}");
-        return true;
+/// Candidates starting with the typed prefix.
+fn candidates(values: &[String], prefix: &str) -> Vec<Pair> {
+    values
+        .iter()
+        .filter(|value| value.starts_with(prefix))
+        .map(|value| Pair {
+            display: value.clone(),
+            replacement: value.clone(),
+        })
+        .collect()
+}
+
+/// Clears the screen and starts a new conversation in the same session.
+pub fn handle_clean(chat: &mut Chat, _args: &str) {
+    chat.session.history.clear();
+    chat.last_response = None;
+    chat.save();
+    print!("\x1b[2J\x1b[H");
+    let _ = std::io::stdout().flush();
+}
+
+pub fn handle_model(chat: &mut Chat, args: &str) {
+    let service = &mut chat.service;
+    match args.trim() {
+        "" => println!("\nmodel: {}\nurl: {}", service.model, service.endpoint),
+        model => {
+            service.model = model.to_string();
+            chat.base_model = model.to_string();
+            println!("\nmodel: {}", service.model);
+        }
     }
-    false
 }
 
-pub fn handle_clean(user_input: &str, history: &mut Vec<String>) -> bool {
-    if user_input == "/clean" {
-        history.clear();
-        print!("\x1b[2J\x1b[H");
-        let _ = std::io::stdout().flush();
-        return true;
+pub fn handle_profile(chat: &mut Chat, args: &str) {
+    let name = args.trim();
+    if name.is_empty() {
+        println!("\nUsage: /profile <name|default>");
+        return;
+    }
+    let switched = if name == "default" {
+        Ok(core::Service::new(chat.args))
+    } else {
+        core::Service::from_profile(chat.args, name)
+    };
+    match switched {
+        Ok(profile) => {
+            chat.service = profile;
+            chat.base_model = chat.service.model.clone();
+            println!("\nprofile: {} ({})", name, chat.service.model);
+        }
+        Err(err) => eprintln!("\n{}", err),
+    }
+}
+
+/// Shows, replaces (`/system <text>` or `/system @file`) or resets the
+/// instructions given to the model, which default to the persona's ones.
+pub fn handle_system(chat: &mut Chat, args: &str) {
+    let default = &chat.persona.instructions;
+    let system = &mut chat.session.system;
+    let value = args.trim();
+    if value.is_empty() {
+        println!("\n{}", system.as_deref().unwrap_or(default));
+        return;
+    }
+    if value == "reset" {
+        *system = None;
+        println!("\nsystem: default");
+        chat.save();
+        return;
+    }
+    let text = match value.strip_prefix('@') {
+        Some(path) => match fs::read_to_string(attach::expand_path(path)) {
+            Ok(text) => text.trim().to_string(),
+            Err(err) => {
+                eprintln!("\nError reading {}: {}", path, err);
+                return;
+            }
+        },
+        None => value.to_string(),
+    };
+    *system = Some(text);
+    println!("\nsystem: updated");
+    chat.save();
+}
+
+/// Lists personas, or switches to one, applying its model and dropping
+/// instructions set with `/system`.
+pub fn handle_persona(chat: &mut Chat, args: &str) {
+    let name = args.trim();
+    if name.is_empty() {
+        println!(
+            "\npersona: {}\navailable: {}",
+            chat.persona.name,
+            list_personas().join(", ")
+        );
+        return;
+    }
+    match Persona::load(name) {
+        Ok(loaded) => {
+            chat.persona = loaded;
+            chat.service.model = chat
+                .persona
+                .model
+                .clone()
+                .unwrap_or_else(|| chat.base_model.clone());
+            chat.session.system = None;
+            chat.session.persona = Some(chat.persona.name.clone());
+            chat.save();
+            println!("\npersona: {} ({})", chat.persona.name, chat.service.model);
+        }
+        Err(err) => eprintln!("\n{}", err),
     }
-    false
 }
 
-pub fn handle_stream(user_input: &str, stream_enabled: &mut bool) -> bool {
-    let Some(rest) = user_input.strip_prefix("/stream") else {
-        return false;
+/// Renders a template and queues it as the next message; `/t` alone lists
+/// the templates. Piped stdin used by the template is not attached again.
+pub fn handle_template(chat: &mut Chat, args: &str) {
+    let args = split_args(args.trim());
+    let Some((name, values)) = args.split_first() else {
+        println!("\n{}", template::format_list(&template::all_templates()));
+        return;
     };
-    let mode = rest.trim().to_lowercase();
+
+    let rendered = Template::load(name).and_then(|template| {
+        let assigned = template.assign(values)?;
+        let stdin = chat.pending_stdin.clone().unwrap_or_default();
+        let text = template.render(&assigned, &stdin, &chat.policy, &mut |cmd| {
+            confirm_yes_no(&mut chat.rl, &mut chat.tty_reader, cmd)
+        })?;
+        if template.uses_stdin(&assigned) {
+            chat.pending_stdin = None;
+        }
+        Ok((text, template.model))
+    });
+    match rendered {
+        Ok((rendered, model)) => {
+            // Values are sent as typed: the body's commands already ran.
+            chat.resend = Some(Resend {
+                input: rendered.text,
+                options: core::RequestOptions {
+                    model,
+                    temperature: None,
+                },
+                run_output: None,
+                inline_output: Some(rendered.command_output),
+                source: Some(rendered.source),
+            });
+        }
+        Err(err) => eprintln!("\n{}", err),
+    }
+}
+
+pub fn handle_stream(chat: &mut Chat, args: &str) {
+    let stream_enabled = &mut chat.stream_enabled;
+    let mode = args.trim().to_lowercase();
     if mode == "on" {
         *stream_enabled = true;
         println!("\nstream: on");
@@ -231,82 +439,428 @@ pub fn handle_stream(user_input: &str, stream_enabled: &mut bool) -> bool {
     } else {
         println!("\nUsage: /stream on|off");
     }
-    true
 }
 
-pub fn handle_add(
-    user_input: &str,
-    history: &mut Vec<String>,
-    pending_stdin: &mut Option<String>,
-) -> bool {
-    let Some(rest) = user_input.strip_prefix("/add") else {
-        return false;
-    };
-    let args = split_args(rest.trim());
+pub fn handle_add(chat: &mut Chat, args: &str) {
+    let args = split_args(args.trim());
     if args.is_empty() {
         println!("\nUsage: /add <path> [path2 path3 ...]");
-        return true;
+        return;
     }
-
-    let mut attachment = String::new();
     for path in args {
-        match fs::read_to_string(&path) {
-            Ok(content) => {
-                attachment.push_str("\n-- FILE: ");
-                attachment.push_str(&path);
-                attachment.push_str(" --\n");
-                attachment.push_str(&content);
-                attachment.push('\n');
-                history.push(format!("Attachment: {}\n{}\n", path, content));
-                println!("\nadded: {}", path);
+        add_session_file(&path, false, &chat.service, &mut chat.files);
+    }
+}
+
+/// Adds a path to the session files, rejecting images the model cannot take.
+fn add_session_file(path: &str, pinned: bool, service: &core::Service, files: &mut SessionFiles) {
+    let file = match files.add(path, pinned) {
+        Ok(file) => file,
+        Err(err) => {
+            eprintln!("\nError reading {}: {}", path, err);
+            return;
+        }
+    };
+    if let Err(err) = service.check_images(&attach::images(&file.attachments)) {
+        eprintln!("\n{}: {}", path, err);
+        if let Some(idx) = files.position(path) {
+            files.remove(idx);
+        }
+        return;
+    }
+    if let Some(summary) = attach::summary(&file.attachments) {
+        println!("\n{}", summary);
+    }
+}
+
+pub fn handle_files(chat: &mut Chat, _args: &str) {
+    println!("\n{}", chat.files.list());
+}
+
+pub fn handle_drop(chat: &mut Chat, args: &str) {
+    let files = &mut chat.files;
+    let args = split_args(args.trim());
+    if args.is_empty() {
+        println!("\nUsage: /drop <path|n> [...]");
+        return;
+    }
+    // Resolve every selector first so indexes refer to the listing the user saw.
+    let mut indexes = Vec::new();
+    for selector in &args {
+        match files.position(selector) {
+            Some(idx) => indexes.push(idx),
+            None => eprintln!("\nnot attached: {}", selector),
+        }
+    }
+    indexes.sort_unstable();
+    indexes.dedup();
+    for idx in indexes.into_iter().rev() {
+        println!("\ndropped: {}", files.remove(idx).token);
+    }
+}
+
+pub fn handle_pin(chat: &mut Chat, args: &str) {
+    let Chat { service, files, .. } = chat;
+    let args = split_args(args.trim());
+    if args.is_empty() {
+        println!("\nUsage: /pin <path|n> [...]");
+        return;
+    }
+    for selector in args {
+        match files.position(&selector) {
+            Some(idx) => {
+                let file = files.get_mut(idx);
+                file.pinned = true;
+                println!("\npinned: {}", file.token);
+            }
+            None => add_session_file(&selector, true, service, files),
+        }
+    }
+}
+
+pub fn handle_refresh(chat: &mut Chat, _args: &str) {
+    let files = &mut chat.files;
+    if files.is_empty() {
+        println!("\nno files attached");
+        return;
+    }
+    for line in files.refresh(true) {
+        println!("\n{}", line);
+    }
+}
+
+pub fn handle_undo(chat: &mut Chat, _args: &str) {
+    let history = &mut chat.session.history;
+    match history.undo() {
+        Some(turn) => {
+            chat.last_response = history.last().map(|turn| turn.response.clone());
+            println!("\nremoved: {}", turn.input);
+            chat.save();
+        }
+        None => println!("\nNo previous message"),
+    }
+}
+
+/// Queues the last message to be answered again, optionally with another
+/// model or temperature. The new answer starts a sibling branch.
+pub fn handle_retry(chat: &mut Chat, args: &str) {
+    let mut options = core::RequestOptions::default();
+    for arg in split_args(args.trim()) {
+        match arg.parse::<f32>() {
+            Ok(temperature) if (0.0..=2.0).contains(&temperature) => {
+                options.temperature = Some(temperature)
+            }
+            Ok(_) => {
+                println!("\nTemperature must be between 0 and 2");
+                return;
+            }
+            Err(_) if options.model.is_none() => options.model = Some(arg),
+            Err(_) => {
+                println!("\nUsage: /retry [model] [temperature]");
+                return;
+            }
+        }
+    }
+    let history = &mut chat.session.history;
+    let Some(turn) = history.rewind() else {
+        println!("\nNo previous message");
+        return;
+    };
+    chat.last_response = history.last().map(|turn| turn.response.clone());
+    chat.resend = Some(Resend {
+        input: turn.input,
+        options,
+        run_output: turn.run_output,
+        inline_output: Some(turn.inline_output),
+        source: turn.source,
+    });
+}
+
+/// Lets the user rewrite the last message on the prompt line (or in
+/// `$EDITOR` with `/edit editor`) and queues it as a sibling branch.
+pub fn handle_edit(chat: &mut Chat, args: &str) {
+    let use_editor = match args.trim() {
+        "" => false,
+        "editor" => true,
+        _ => {
+            println!("\nUsage: /edit [editor]");
+            return;
+        }
+    };
+    let Some(turn) = chat.session.history.last() else {
+        println!("\nNo previous message");
+        return;
+    };
+
+    let edited = if use_editor {
+        edit_in_editor(&turn.input).map(Some)
+    } else {
+        let initial = turn.input.clone();
+        edit_line(&mut chat.rl, &mut chat.tty_reader, &initial)
+    };
+    let input = match edited {
+        Ok(Some(text)) if !text.is_empty() => text,
+        Ok(_) => {
+            println!("\ncancelled");
+            return;
+        }
+        Err(err) => {
+            eprintln!("\n{}", err);
+            return;
+        }
+    };
+
+    // Only leave the old exchange once there is a message to replace it.
+    let history = &mut chat.session.history;
+    let Some(turn) = history.rewind() else {
+        return;
+    };
+    chat.last_response = history.last().map(|turn| turn.response.clone());
+    chat.resend = Some(Resend {
+        input,
+        options: core::RequestOptions::default(),
+        run_output: turn.run_output,
+        // The message may have changed, so its inline commands run again.
+        inline_output: None,
+        source: None,
+    });
+}
+
+/// Composes a message in `$EDITOR`, starting from the given text, and
+/// queues it to be sent.
+pub fn handle_editor(chat: &mut Chat, args: &str) {
+    match edit_in_editor(args.trim()) {
+        Ok(text) if !text.is_empty() => {
+            println!("{}", text);
+            chat.resend = Some(Resend {
+                input: text,
+                options: core::RequestOptions::default(),
+                run_output: chat.pending_output.take(),
+                inline_output: None,
+                source: None,
+            });
+        }
+        Ok(_) => println!("\ncancelled"),
+        Err(err) => eprintln!("\n{}", err),
+    }
+}
+
+pub fn handle_branches(chat: &mut Chat, _args: &str) {
+    println!("\n{}", chat.session.history.branches());
+}
+
+pub fn handle_tree(chat: &mut Chat, _args: &str) {
+    println!("\n{}", chat.session.history.tree());
+}
+
+pub fn handle_checkout(chat: &mut Chat, args: &str) {
+    let Ok(id) = args.trim().parse::<usize>() else {
+        println!("\nUsage: /checkout <id>");
+        return;
+    };
+    match chat.session.history.checkout(id) {
+        Ok(turn) => {
+            chat.last_response = Some(turn.response.clone());
+            println!("\nchecked out {}: {}", id, turn.input);
+            chat.save();
+        }
+        Err(err) => println!("\n{}", err),
+    }
+}
+
+pub fn handle_code(chat: &mut Chat, args: &str) {
+    let args = split_args(args.trim());
+    if args.len() > 2 {
+        println!("\nUsage: /code [N|lang] [path]");
+        return;
+    }
+    let Some(response) = chat.last_response.as_deref() else {
+        println!("\nNo previous answer");
+        return;
+    };
+
+    // A lone argument is a selector unless it looks like a file path.
+    let (selector, path) = match args.as_slice() {
+        [] => (extract::CodeSelector::First, None),
+        [one] if one.contains('/') || one.contains('.') => {
+            (extract::CodeSelector::First, Some(one.as_str()))
+        }
+        [one] => match extract::parse_code_selector(one) {
+            Ok(selector) => (selector, None),
+            Err(err) => {
+                println!("\nError: {}", err);
+                return;
             }
+        },
+        [one, two, ..] => match extract::parse_code_selector(one) {
+            Ok(selector) => (selector, Some(two.as_str())),
             Err(err) => {
-                eprintln!("\nError reading {}: {}", path, err);
+                println!("\nError: {}", err);
+                return;
             }
+        },
+    };
+
+    let code = match extract::extract_code(response, &selector) {
+        Ok(code) => code,
+        Err(err) => {
+            println!("\nError: {}", err);
+            return;
         }
+    };
+
+    match path {
+        Some(path) => match fs::write(path, format!("{}\n", code)) {
+            Ok(()) => println!("\nwritten: {}", path),
+            Err(err) => eprintln!("\nError writing {}: {}", path, err),
+        },
+        None => println!("\n{}", code),
     }
+}
 
-    if !attachment.is_empty() {
-        *pending_stdin = Some(attachment);
+pub fn handle_export(chat: &mut Chat, args: &str) {
+    let args = split_args(args.trim());
+    // The format may be left out when the path's extension names it.
+    let (format, path) = match args.as_slice() {
+        [] => (None, None),
+        [one] => match export::Format::from_name(one) {
+            Some(format) => (Some(format), None),
+            None => (None, Some(one.as_str())),
+        },
+        [format, path] => match export::Format::from_name(format) {
+            Some(format) => (Some(format), Some(path.as_str())),
+            None => {
+                println!("\nUsage: /export [md|json|html] [path]");
+                return;
+            }
+        },
+        _ => {
+            println!("\nUsage: /export [md|json|html] [path]");
+            return;
+        }
+    };
+    match export::write(&chat.session, format, path) {
+        Ok(path) => println!("\nexported: {}", path.display()),
+        Err(err) => println!("\nError: {}", err),
     }
-    true
 }
 
-pub fn handle_eval(user_input: &str) -> bool {
-    let Some(rest) = user_input.strip_prefix("/eval") else {
-        return false;
+pub fn handle_copy(chat: &mut Chat, args: &str) {
+    let args = split_args(args.trim());
+    let Some(response) = chat.last_response.as_deref() else {
+        println!("\nNo previous answer");
+        return;
+    };
+
+    let text = match args.as_slice() {
+        [] => response.to_string(),
+        [kind, selector @ ..] if kind == "code" && selector.len() <= 1 => {
+            let selector = match selector.first() {
+                Some(value) => extract::parse_code_selector(value),
+                None => Ok(extract::CodeSelector::First),
+            };
+            match selector.and_then(|selector| extract::extract_code(response, &selector)) {
+                Ok(code) => code,
+                Err(err) => {
+                    println!("\nError: {}", err);
+                    return;
+                }
+            }
+        }
+        _ => {
+            println!("\nUsage: /copy [code [N|lang]]");
+            return;
+        }
+    };
+
+    match utils::copy_to_clipboard(&text) {
+        Ok(bytes) => println!("\ncopied: {} bytes", bytes),
+        Err(err) => println!("\nError: {}", err),
+    }
+}
+
+pub fn handle_run(chat: &mut Chat, args: &str) {
+    let index = match args.trim() {
+        "" => 1,
+        value => match value.parse::<usize>() {
+            Ok(index) if index > 0 => index,
+            _ => {
+                println!("\nUsage: /run [N]");
+                return;
+            }
+        },
+    };
+    let Some(response) = chat.last_response.as_deref() else {
+        println!("\nNo previous answer");
+        return;
+    };
+
+    let command = match extract::extract_shell_code(response, index) {
+        Ok(command) => command,
+        Err(err) => {
+            println!("\nError: {}", err);
+            return;
+        }
     };
-    let expr = strip_inline_commands(rest).trim().to_string();
+
+    let command = match confirm_command(&mut chat.rl, &mut chat.tty_reader, &command) {
+        Ok(Some(command)) => command,
+        Ok(None) => {
+            println!("\ncancelled");
+            return;
+        }
+        Err(err) => {
+            eprintln!("\n{}", err);
+            return;
+        }
+    };
+
+    // Output is shown now and attached to the next prompt as COMMAND OUTPUT.
+    // The user already confirmed, but allow/deny patterns and read-only mode still apply.
+    let command = InlineCommand {
+        command,
+        sandboxed: false,
+    };
+    if let Some(output) = run_commands(&[command], &chat.policy, &mut |_| true) {
+        println!("\n{}", output);
+        add_pending_output(&mut chat.pending_output, output);
+    }
+}
+
+/// Appends command output to what is sent with the next message.
+pub fn add_pending_output(pending_output: &mut Option<String>, output: String) {
+    match pending_output {
+        Some(existing) => {
+            existing.push_str("\n\n");
+            existing.push_str(&output);
+        }
+        None => *pending_output = Some(output),
+    }
+}
+
+pub fn handle_eval(_chat: &mut Chat, args: &str) {
+    let expr = strip_inline_commands(args).trim().to_string();
     if expr.is_empty() {
         println!("\nUsage: /eval <expression>");
-        return true;
+        return;
     }
 
     match eval_expr(&expr) {
         Ok(value) => println!("\n{}", value),
         Err(err) => println!("\nError: {}", format_eval_error(err)),
     }
-
-    true
 }
 
-pub async fn handle_trans(
-    user_input: &str,
-    service: &core::Service,
-    args: &core::Cli,
-) -> Result<bool, String> {
-    let Some(rest) = user_input.strip_prefix("/trans") else {
-        return Ok(false);
-    };
-    let raw_text = strip_inline_commands(rest).trim().to_string();
+pub async fn handle_trans(chat: &mut Chat<'_>, args: &str) -> Result<(), String> {
+    let raw_text = strip_inline_commands(args).trim().to_string();
     if raw_text.is_empty() {
         println!("\nUsage: /trans [INPUT_LANG:OUTPUT_LANG] <text>");
-        return Ok(true);
+        return Ok(());
     }
 
     let (input_lang, output_lang, text) = parse_lang_directive(&raw_text);
     if text.is_empty() {
-        return Ok(true);
+        return Ok(());
     }
 
     let user_lang = normalize_lang_tag(&utils::get_user_lang());
@@ -321,8 +875,7 @@ pub async fn handle_trans(
     let target_lang_name = lang_display_name(&target_lang);
 
     let prompt = format!(
-        "
-Task: Translate the following text faithfully, preserving its meaning and context.
+        "\nTask: Translate the following text faithfully, preserving its meaning and context.
 Return only the translation. Do not explain or add anything.
 You must translate. Do not choose any other task or language.
 LANG: {}:{}.
@@ -335,34 +888,26 @@ TEXT:
         source_lang, target_lang, source_lang, target_lang, target_lang_name, text
     );
 
-    if args.verbose {
+    if chat.args.verbose {
         println!("\x1b[32m{}\x1b[0m", prompt);
     }
 
-    match service.complete(&prompt).await {
+    match chat.service.complete(&prompt).await {
         Ok(text) => {
             let output = render::render_markdown(&text);
             println!("\n{}", output);
-            Ok(true)
+            Ok(())
         }
         Err(err) => Err(format!("AI error: {}", err)),
     }
 }
 
-pub async fn handle_save(
-    user_input: &str,
-    service: &core::Service,
-    args: &core::Cli,
-    history: &[String],
-) -> Result<bool, String> {
-    let Some(rest) = user_input.strip_prefix("/save") else {
-        return Ok(false);
-    };
-    let raw_text = strip_inline_commands(rest).trim().to_string();
+pub async fn handle_save(chat: &mut Chat<'_>, args: &str) -> Result<(), String> {
+    let raw_text = strip_inline_commands(args).trim().to_string();
 
     let datetime = utils::current_datetime();
     let user_lang = utils::get_user_lang();
-    let history_text = history.join("\n");
+    let history_text = chat.session.history.render(&utils::get_user());
     let prompt = if raw_text.is_empty() {
         format!(
             "Write an informe for the user.\n\
@@ -380,11 +925,11 @@ Chat history:\n\
         )
     };
 
-    if args.verbose {
+    if chat.args.verbose {
         println!("\x1b[32m{}\x1b[0m", prompt);
     }
 
-    let result = match service.complete(&prompt).await {
+    let result = match chat.service.complete(&prompt).await {
         Ok(text) => text,
         Err(err) => return Err(format!("AI error: {}", err)),
     };
@@ -402,7 +947,7 @@ Chat history:\n\
     match write_result {
         Ok(()) => {
             println!("\nsaved: {}", path);
-            Ok(true)
+            Ok(())
         }
         Err(err) => Err(format!("File error: {}", err)),
     }
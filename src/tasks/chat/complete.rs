@@ -0,0 +1,107 @@
+//! Completion candidates for `#!(...)` commands, looked up once per chat.
+use std::cell::OnceCell;
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+use crate::tasks::inline::policy::ExecLimits;
+
+/// Git subcommands offered when `git --list-cmds` is not available.
+const GIT_SUBCOMMANDS: &[&str] = &[
+    "status", "add", "commit", "push", "pull", "fetch", "log", "diff", "show", "branch",
+    "checkout", "switch", "merge", "rebase", "stash", "reset", "restore",
+];
+
+/// Executables, git subcommands and refs, each read on first use and kept
+/// for the rest of the chat.
+pub struct ShellCandidates {
+    /// Directory inline commands run in, used for git lookups.
+    cwd: Option<PathBuf>,
+    executables: OnceCell<Vec<String>>,
+    git_subcommands: OnceCell<Vec<String>>,
+    git_refs: OnceCell<Vec<String>>,
+}
+
+impl ShellCandidates {
+    pub fn new() -> Self {
+        Self {
+            cwd: ExecLimits::from_env().cwd,
+            executables: OnceCell::new(),
+            git_subcommands: OnceCell::new(),
+            git_refs: OnceCell::new(),
+        }
+    }
+
+    /// Names of the executable files in `$PATH`, sorted.
+    pub fn executables(&self) -> &[String] {
+        self.executables.get_or_init(path_executables)
+    }
+
+    /// Git commands and aliases, or a fixed list when git cannot list them.
+    pub fn git_subcommands(&self) -> &[String] {
+        self.git_subcommands.get_or_init(|| {
+            self.git(&["--list-cmds=main,others,alias,nohelpers"])
+                .unwrap_or_else(|| GIT_SUBCOMMANDS.iter().map(|cmd| cmd.to_string()).collect())
+        })
+    }
+
+    /// Branches, remote branches and tags of the repository, empty outside one.
+    pub fn git_refs(&self) -> &[String] {
+        self.git_refs.get_or_init(|| {
+            self.git(&[
+                "for-each-ref",
+                "--format=%(refname:short)",
+                "refs/heads",
+                "refs/remotes",
+                "refs/tags",
+            ])
+            .unwrap_or_default()
+        })
+    }
+
+    /// Output lines of a git command, sorted and deduplicated.
+    fn git(&self, args: &[&str]) -> Option<Vec<String>> {
+        let mut command = Command::new("git");
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        let output = command
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())?;
+        let lines: BTreeSet<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect();
+        Some(lines.into_iter().collect())
+    }
+}
+
+fn path_executables() -> Vec<String> {
+    let Some(path) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+    let mut names = BTreeSet::new();
+    for dir in env::split_paths(&path) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(Result::ok) {
+            // Follows symlinks, so linked executables are included.
+            let is_executable = fs::metadata(entry.path())
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0);
+            if is_executable && let Some(name) = entry.file_name().to_str() {
+                names.insert(name.to_string());
+            }
+        }
+    }
+    names.into_iter().collect()
+}
@@ -0,0 +1,293 @@
+//! Verbatim transcripts of chat sessions as Markdown, JSON or HTML.
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use super::history::Turn;
+use super::session::Session;
+
+/// File format of an exported transcript.
+#[derive(Clone, Copy)]
+pub enum Format {
+    Markdown,
+    Json,
+    Html,
+}
+
+impl Format {
+    /// Parses `md`, `markdown`, `json` or `html`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "json" => Some(Self::Json),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+
+    /// Format matching the extension of `path`, if any.
+    fn from_path(path: &str) -> Option<Self> {
+        let (_, ext) = path.rsplit_once('.')?;
+        Self::from_name(ext)
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// Transcript fields written by the JSON format.
+#[derive(Serialize)]
+struct Transcript<'a> {
+    id: &'a str,
+    created: &'a str,
+    persona: Option<&'a str>,
+    system: Option<&'a str>,
+    turns: Vec<&'a Turn>,
+}
+
+/// Renders the active branch of the session, every message as it was typed.
+pub fn render(session: &Session, format: Format) -> Result<String, String> {
+    let turns = session.history.active();
+    match format {
+        Format::Markdown => Ok(markdown(session, &turns)),
+        Format::Html => Ok(html(session, &turns)),
+        Format::Json => serde_json::to_string_pretty(&Transcript {
+            id: &session.id,
+            created: &session.created,
+            persona: session.persona.as_deref(),
+            system: session.system.as_deref(),
+            turns,
+        })
+        .map_err(|err| err.to_string()),
+    }
+}
+
+/// Writes the transcript to `path`, or to `netero.<session id>.<ext>` in the
+/// current directory. Without a format it follows the path's extension,
+/// falling back to Markdown.
+pub fn write(
+    session: &Session,
+    format: Option<Format>,
+    path: Option<&str>,
+) -> Result<PathBuf, String> {
+    if session.history.active().is_empty() {
+        return Err("nothing to export".to_string());
+    }
+    let format = format
+        .or_else(|| path.and_then(Format::from_path))
+        .unwrap_or(Format::Markdown);
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(format!("netero.{}.{}", session.id, format.extension())),
+    };
+    let text = render(session, format)?;
+    fs::write(&path, text).map_err(|err| format!("{}: {}", path.display(), err))?;
+    Ok(path)
+}
+
+/// Loads a saved session (`last` for the newest) and prints its transcript,
+/// or writes it to `output`.
+pub fn export_session(id: &str, format: Option<&str>, output: Option<&str>) -> Result<(), String> {
+    let format = match format {
+        Some(name) => Some(
+            Format::from_name(name)
+                .ok_or_else(|| format!("unknown format '{}', expected md, json or html", name))?,
+        ),
+        None => None,
+    };
+    let session = Session::load(id)?;
+    match output {
+        Some(path) => {
+            let path = write(&session, format, Some(path))?;
+            eprintln!("exported: {}", path.display());
+        }
+        None => {
+            let text = render(&session, format.unwrap_or(Format::Markdown))?;
+            // A closed pipe (`| head`) just ends the output.
+            let _ = writeln!(io::stdout(), "{}", text);
+        }
+    }
+    Ok(())
+}
+
+fn markdown(session: &Session, turns: &[&Turn]) -> String {
+    let mut out = format!("# Chat {}\n\n", session.id);
+    out.push_str(&format!("- Created: {}\n", session.created));
+    if let Some(persona) = &session.persona {
+        out.push_str(&format!("- Persona: {}\n", persona));
+    }
+    if let Some(system) = &session.system {
+        out.push_str(&format!("\nSystem instructions:\n\n{}\n", fenced(system)));
+    }
+    for turn in turns {
+        out.push_str(&format!("\n## User{}\n\n", when(turn)));
+        out.push_str(turn.input.trim_end());
+        out.push('\n');
+        if !turn.attachments.is_empty() {
+            out.push_str("\nAttachments:\n\n");
+            for label in &turn.attachments {
+                out.push_str(&format!("- `{}`\n", label));
+            }
+        }
+        for (title, output) in command_outputs(turn) {
+            out.push_str(&format!("\n{}:\n\n{}\n", title, fenced(output)));
+        }
+        out.push_str(&format!("\n## Assistant ({})\n\n", model(turn)));
+        out.push_str(turn.response.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn html(session: &Session, turns: &[&Turn]) -> String {
+    let title = format!("Chat {}", escape_html(&session.id));
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+<style>body{{font-family:sans-serif;max-width:50em;margin:auto}}\
+pre{{white-space:pre-wrap;background:#f4f4f4;padding:.5em}}</style>\n</head>\n<body>\n\
+<h1>{title}</h1>\n<p>Created: {}</p>\n",
+        escape_html(&session.created)
+    );
+    if let Some(persona) = &session.persona {
+        out.push_str(&format!("<p>Persona: {}</p>\n", escape_html(persona)));
+    }
+    if let Some(system) = &session.system {
+        out.push_str(&format!(
+            "<p>System instructions:</p>\n<pre>{}</pre>\n",
+            escape_html(system)
+        ));
+    }
+    for turn in turns {
+        out.push_str(&format!(
+            "<h2>User{}</h2>\n<pre>{}</pre>\n",
+            escape_html(&when(turn)),
+            escape_html(turn.input.trim_end())
+        ));
+        if !turn.attachments.is_empty() {
+            out.push_str("<p>Attachments:</p>\n<ul>\n");
+            for label in &turn.attachments {
+                out.push_str(&format!("<li><code>{}</code></li>\n", escape_html(label)));
+            }
+            out.push_str("</ul>\n");
+        }
+        for (title, output) in command_outputs(turn) {
+            out.push_str(&format!(
+                "<p>{}:</p>\n<pre>{}</pre>\n",
+                title,
+                escape_html(output)
+            ));
+        }
+        out.push_str(&format!(
+            "<h2>Assistant ({})</h2>\n<pre>{}</pre>\n",
+            escape_html(model(turn)),
+            escape_html(turn.response.trim_end())
+        ));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// ` (time)` for turns saved with a timestamp.
+fn when(turn: &Turn) -> String {
+    if turn.time.is_empty() {
+        String::new()
+    } else {
+        format!(" ({})", turn.time)
+    }
+}
+
+fn model(turn: &Turn) -> &str {
+    if turn.model.is_empty() {
+        "unknown model"
+    } else {
+        &turn.model
+    }
+}
+
+/// `/run` and inline command output sent with the message.
+fn command_outputs(turn: &Turn) -> Vec<(&'static str, &str)> {
+    [
+        ("Run output", turn.run_output.as_deref()),
+        ("Inline command output", turn.inline_output.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(title, output)| Some((title, output?.trim_end())))
+    .collect()
+}
+
+/// Wraps text in a code fence longer than any backtick run inside it.
+fn fenced(text: &str) -> String {
+    let longest = text.split(|ch| ch != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}text\n{}\n{}", fence, text.trim_end(), fence)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(input: &str, response: &str, attachments: &[&str]) -> Session {
+        let mut session = Session::new();
+        session.history.push(Turn {
+            model: "m1".to_string(),
+            input: input.to_string(),
+            cleaned: input.to_string(),
+            response: response.to_string(),
+            run_output: None,
+            inline_output: Some("```\nnested\n```".to_string()),
+            time: String::new(),
+            attachments: attachments.iter().map(|label| label.to_string()).collect(),
+            source: None,
+        });
+        session
+    }
+
+    #[test]
+    fn fences_outgrow_backtick_runs_in_the_text() {
+        assert_eq!(fenced("plain"), "```text\nplain\n```");
+        assert_eq!(fenced("a ```` b"), "`````text\na ```` b\n`````");
+        let markdown = render(&session("hi", "ok", &[]), Format::Markdown).unwrap();
+        assert!(markdown.contains("Inline command output:\n\n````text\n```\nnested\n```\n````"));
+    }
+
+    #[test]
+    fn html_escapes_messages_and_labels() {
+        let session = session("<b>&\"x\"</b>", "a < b", &["./<x>.rs"]);
+        let html = render(&session, Format::Html).unwrap();
+        assert!(html.contains("<pre>&lt;b&gt;&amp;&quot;x&quot;&lt;/b&gt;</pre>"));
+        assert!(html.contains("<pre>a &lt; b</pre>"));
+        assert!(html.contains("<li><code>./&lt;x&gt;.rs</code></li>"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn json_keeps_the_turns_as_saved() {
+        let json = render(&session("hi", "ok", &["./a.rs"]), Format::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["turns"][0]["input"], "hi");
+        assert_eq!(value["turns"][0]["attachments"][0], "./a.rs");
+    }
+
+    #[test]
+    fn empty_sessions_have_nothing_to_export() {
+        assert_eq!(
+            write(&Session::new(), None, Some("/nonexistent/out.md")).unwrap_err(),
+            "nothing to export"
+        );
+        assert!(Format::from_path("out.HTML").is_some());
+        assert!(Format::from_name("pdf").is_none());
+    }
+}
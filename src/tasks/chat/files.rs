@@ -0,0 +1,147 @@
+use std::fs;
+use std::time::SystemTime;
+
+use crate::tasks::attach::{self, Attachment};
+use crate::utils::format_size;
+
+/// File, directory or glob attached to the chat with `/add` or `/pin`.
+pub struct SessionFile {
+    /// Path as written by the user, used to read it again.
+    pub token: String,
+    pub attachments: Vec<Attachment>,
+    /// Pinned files are re-read from disk before each turn when they change.
+    pub pinned: bool,
+    /// Latest modification time seen when the contents were read.
+    modified: Option<SystemTime>,
+}
+
+impl SessionFile {
+    fn read(token: &str, pinned: bool) -> Result<Self, String> {
+        let attachments = attach::attach_path(token)?;
+        Ok(Self {
+            token: token.to_string(),
+            modified: latest_modified(token, &attachments),
+            attachments,
+            pinned,
+        })
+    }
+
+    /// True when the file changed on disk since it was read.
+    pub fn is_stale(&self) -> bool {
+        latest_modified(&self.token, &self.attachments) != self.modified
+    }
+
+    pub fn size(&self) -> usize {
+        self.attachments.iter().map(Attachment::size).sum()
+    }
+}
+
+/// Attachments kept for the whole chat session and sent with every turn.
+#[derive(Default)]
+pub struct SessionFiles {
+    items: Vec<SessionFile>,
+}
+
+impl SessionFiles {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Reads and adds a path, replacing an earlier entry for the same path.
+    pub fn add(&mut self, token: &str, pinned: bool) -> Result<&SessionFile, String> {
+        let file = SessionFile::read(token, pinned)?;
+        let idx = match self.position(token) {
+            Some(idx) => {
+                self.items[idx] = file;
+                idx
+            }
+            None => {
+                self.items.push(file);
+                self.items.len() - 1
+            }
+        };
+        Ok(&self.items[idx])
+    }
+
+    /// Finds an entry by 1-based index or by path.
+    pub fn position(&self, selector: &str) -> Option<usize> {
+        if let Ok(index) = selector.parse::<usize>() {
+            return index.checked_sub(1).filter(|idx| *idx < self.items.len());
+        }
+        self.items.iter().position(|item| item.token == selector)
+    }
+
+    pub fn get_mut(&mut self, idx: usize) -> &mut SessionFile {
+        &mut self.items[idx]
+    }
+
+    pub fn remove(&mut self, idx: usize) -> SessionFile {
+        self.items.remove(idx)
+    }
+
+    /// Re-reads entries from disk (only pinned and changed ones unless `all`),
+    /// returning a status line per re-read entry.
+    pub fn refresh(&mut self, all: bool) -> Vec<String> {
+        let mut report = Vec::new();
+        for item in &mut self.items {
+            if !(all || (item.pinned && item.is_stale())) {
+                continue;
+            }
+            match SessionFile::read(&item.token, item.pinned) {
+                Ok(file) => {
+                    *item = file;
+                    report.push(format!("refreshed: {}", item.token));
+                }
+                Err(err) => report.push(format!("Error reading {}: {}", item.token, err)),
+            }
+        }
+        report
+    }
+
+    /// Attachments of every entry, in the order they were added.
+    pub fn attachments(&self) -> Vec<&Attachment> {
+        self.items
+            .iter()
+            .flat_map(|item| item.attachments.iter())
+            .collect()
+    }
+
+    /// Numbered listing with sizes and pinned/stale markers.
+    pub fn list(&self) -> String {
+        if self.items.is_empty() {
+            return "no files attached".to_string();
+        }
+        self.items
+            .iter()
+            .enumerate()
+            .map(|(idx, item)| {
+                let mut line = format!(
+                    "{:>3}. {} ({})",
+                    idx + 1,
+                    item.attachments
+                        .first()
+                        .filter(|_| item.attachments.len() == 1)
+                        .map_or(item.token.clone(), Attachment::label),
+                    format_size(item.size() as u64)
+                );
+                if item.pinned {
+                    line.push_str(" [pinned]");
+                }
+                if item.is_stale() {
+                    line.push_str(" [changed on disk]");
+                }
+                line
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Most recent modification time of the path and the files it attached.
+fn latest_modified(token: &str, attachments: &[Attachment]) -> Option<SystemTime> {
+    std::iter::once(token)
+        .chain(attachments.iter().map(|a| a.path.as_str()))
+        .filter_map(|path| fs::metadata(attach::expand_path(path)).ok())
+        .filter_map(|meta| meta.modified().ok())
+        .max()
+}
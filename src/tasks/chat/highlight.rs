@@ -0,0 +1,115 @@
+//! Coloring of chat input: slash commands, inline directives and paths.
+use std::borrow::Cow;
+
+use crate::tasks::attach;
+
+/// Color of typed input, the same as the prompt marker.
+const INPUT: &str = "\x1b[36m";
+const COMMAND: &str = "\x1b[1;36m";
+const INLINE: &str = "\x1b[33m";
+const PATH_FOUND: &str = "\x1b[32m";
+const PATH_MISSING: &str = "\x1b[31m";
+
+/// Colors a known slash command at the start of the line, `#!(...)`,
+/// `#!ro(...)` and `#!{...}` directives, and path tokens (green when the path
+/// exists, red when it does not).
+pub fn highlight_input<'l>(line: &'l str, commands: &[String]) -> Cow<'l, str> {
+    let mut spans: Vec<(usize, usize, &str)> = Vec::new();
+    let command_end = line.find(char::is_whitespace).unwrap_or(line.len());
+    let first = &line[..command_end];
+    if first.starts_with('/') && commands.iter().any(|cmd| cmd == first) {
+        spans.push((0, command_end, COMMAND));
+    }
+
+    let directives = directive_spans(line);
+    let mut offset = 0;
+    for token in line.split_whitespace() {
+        let start = offset + line[offset..].find(token).unwrap_or(0);
+        offset = start + token.len();
+        // A slash command, or the start of one still being typed, is not a path.
+        if start == 0 && commands.iter().any(|cmd| cmd.starts_with(token)) {
+            continue;
+        }
+        if directives.iter().any(|(s, e)| start < *e && offset > *s) {
+            continue;
+        }
+        if let Some(exists) = attach::path_exists(token) {
+            let color = if exists { PATH_FOUND } else { PATH_MISSING };
+            spans.push((start, offset, color));
+        }
+    }
+    spans.extend(directives.iter().map(|(start, end)| (*start, *end, INLINE)));
+
+    if spans.is_empty() {
+        return Cow::Borrowed(line);
+    }
+    spans.sort_by_key(|(start, _, _)| *start);
+    let mut out = String::with_capacity(line.len() + spans.len() * 16);
+    let mut last = 0;
+    for (start, end, color) in spans {
+        out.push_str(&line[last..start]);
+        out.push_str(color);
+        out.push_str(&line[start..end]);
+        out.push_str("\x1b[0m");
+        out.push_str(INPUT);
+        last = end;
+    }
+    out.push_str(&line[last..]);
+    Cow::Owned(out)
+}
+
+/// Byte ranges of inline directives. An unterminated directive runs to the
+/// end of the line, since it is usually still being typed.
+fn directive_spans(line: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while let Some(found) = line[i..].find("#!") {
+        let start = i + found;
+        let rest = &line[start + 2..];
+        let (open, close) = if rest.starts_with('(') {
+            (start + 3, ')')
+        } else if rest.starts_with("ro(") {
+            (start + 5, ')')
+        } else if rest.starts_with('{') {
+            (start + 3, '}')
+        } else {
+            i = start + 2;
+            continue;
+        };
+        let end = closing(line, open, close, true)
+            .or_else(|| closing(line, open, close, false))
+            .map_or(line.len(), |end| end + 1);
+        spans.push((start, end));
+        i = end;
+    }
+    spans
+}
+
+/// Finds the delimiter closing a directive body, skipping nested parentheses
+/// and, with `quoted`, quoted text.
+fn closing(line: &str, start: usize, close: char, quoted: bool) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escape = false;
+    for (idx, ch) in line[start..].char_indices() {
+        if escape {
+            escape = false;
+        } else if ch == '\\' && quote != Some('\'') {
+            escape = true;
+        } else if let Some(q) = quote {
+            if ch == q {
+                quote = None;
+            }
+        } else if quoted && close == ')' && (ch == '"' || ch == '\'') {
+            quote = Some(ch);
+        } else if close == ')' && ch == '(' {
+            depth += 1;
+        } else if ch == close {
+            if depth == 0 {
+                return Some(start + idx);
+            }
+            depth -= 1;
+        }
+    }
+    None
+}
@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::RequestOptions;
+
+/// Characters of a message shown in branch listings.
+const SNIPPET_CHARS: usize = 60;
+
+/// One exchange of the chat: what the user typed and what came back.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Turn {
+    /// Model that wrote the response.
+    #[serde(default)]
+    pub model: String,
+    /// Message as typed, with inline commands and attachments.
+    pub input: String,
+    /// Message sent as the user's line of the dialog.
+    pub cleaned: String,
+    pub response: String,
+    /// Output of `/run` sent along with the message.
+    pub run_output: Option<String>,
+    /// Output of the inline commands of the message.
+    pub inline_output: Option<String>,
+    /// When the message was sent.
+    #[serde(default)]
+    pub time: String,
+    /// Files attached to the message, pinned ones included.
+    #[serde(default)]
+    pub attachments: Vec<String>,
+    /// Template body the message was rendered from; attachments come from it
+    /// rather than from the substituted values in `input`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+impl Turn {
+    /// First line of the message, shortened for listings.
+    fn snippet(&self) -> String {
+        let line = self.input.lines().next().unwrap_or("");
+        if line.chars().count() > SNIPPET_CHARS {
+            let cut: String = line.chars().take(SNIPPET_CHARS).collect();
+            format!("{}...", cut)
+        } else {
+            line.to_string()
+        }
+    }
+}
+
+/// Exchange in the history tree; siblings are alternative continuations.
+#[derive(Serialize, Deserialize)]
+struct Node {
+    id: usize,
+    parent: Option<usize>,
+    turn: Turn,
+}
+
+/// Exchanges of the chat stored as a tree. `head` is the last exchange of the
+/// active branch, and the dialog sent to the model is the path leading to it.
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+    nodes: Vec<Node>,
+    head: Option<usize>,
+    next_id: usize,
+}
+
+impl History {
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Adds an exchange after the head and makes it the new head.
+    pub fn push(&mut self, turn: Turn) {
+        self.next_id += 1;
+        self.nodes.push(Node {
+            id: self.next_id,
+            parent: self.head,
+            turn,
+        });
+        self.head = Some(self.next_id);
+    }
+
+    pub fn last(&self) -> Option<&Turn> {
+        self.head
+            .and_then(|id| self.node(id))
+            .map(|node| &node.turn)
+    }
+
+    /// Moves the head back one exchange, keeping it as a branch, and returns it.
+    pub fn rewind(&mut self) -> Option<Turn> {
+        let node = self.node(self.head?)?;
+        let turn = node.turn.clone();
+        self.head = node.parent;
+        Some(turn)
+    }
+
+    /// Moves the head back one exchange and deletes it unless other
+    /// branches continue from it.
+    pub fn undo(&mut self) -> Option<Turn> {
+        let id = self.head?;
+        let turn = self.rewind()?;
+        if !self.nodes.iter().any(|node| node.parent == Some(id)) {
+            self.nodes.retain(|node| node.id != id);
+        }
+        Some(turn)
+    }
+
+    /// Starts a new conversation; earlier branches stay in the tree.
+    pub fn clear(&mut self) {
+        self.head = None;
+    }
+
+    /// Makes any exchange the head, so the next message continues from it.
+    pub fn checkout(&mut self, id: usize) -> Result<&Turn, String> {
+        let idx = self
+            .nodes
+            .iter()
+            .position(|node| node.id == id)
+            .ok_or_else(|| format!("no exchange with id {}", id))?;
+        self.head = Some(id);
+        Ok(&self.nodes[idx].turn)
+    }
+
+    /// Exchanges of the active branch, oldest first.
+    pub fn active(&self) -> Vec<&Turn> {
+        self.path(self.head)
+            .into_iter()
+            .map(|node| &node.turn)
+            .collect()
+    }
+
+    /// Renders the active branch as the dialog text given to the model.
+    pub fn render(&self, user: &str) -> String {
+        self.active()
+            .iter()
+            .map(|turn| format!("{}: {}\nAssistant: {}\n", user, turn.cleaned, turn.response))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// One line per branch tip, marking the branch that holds the head.
+    pub fn branches(&self) -> String {
+        let active: Vec<usize> = self.path(self.head).iter().map(|node| node.id).collect();
+        let lines: Vec<String> = self
+            .nodes
+            .iter()
+            .filter(|node| !self.nodes.iter().any(|child| child.parent == Some(node.id)))
+            .map(|leaf| {
+                let marker = if active.contains(&leaf.id) { '*' } else { ' ' };
+                format!(
+                    "{} {:>3}  ({} turns) {}",
+                    marker,
+                    leaf.id,
+                    self.path(Some(leaf.id)).len(),
+                    leaf.turn.snippet()
+                )
+            })
+            .collect();
+        if lines.is_empty() {
+            return "no messages yet".to_string();
+        }
+        lines.join("\n")
+    }
+
+    /// Draws the whole tree, marking the head with `*`.
+    pub fn tree(&self) -> String {
+        if self.nodes.is_empty() {
+            return "no messages yet".to_string();
+        }
+        let mut out = Vec::new();
+        for root in self.children(None) {
+            self.draw(root, "", None, &mut out);
+        }
+        out.join("\n")
+    }
+
+    /// Appends `node` and its descendants; `last` is `None` for roots.
+    fn draw(&self, node: &Node, prefix: &str, last: Option<bool>, out: &mut Vec<String>) {
+        let (branch, indent) = match last {
+            None => ("", ""),
+            Some(true) => ("└─ ", "   "),
+            Some(false) => ("├─ ", "│  "),
+        };
+        let marker = if self.head == Some(node.id) { "*" } else { "" };
+        out.push(format!(
+            "{}{}{}{} {}",
+            prefix,
+            branch,
+            node.id,
+            marker,
+            node.turn.snippet()
+        ));
+        let prefix = format!("{}{}", prefix, indent);
+        let children = self.children(Some(node.id));
+        for (idx, child) in children.iter().enumerate() {
+            self.draw(child, &prefix, Some(idx + 1 == children.len()), out);
+        }
+    }
+
+    fn children(&self, parent: Option<usize>) -> Vec<&Node> {
+        self.nodes
+            .iter()
+            .filter(|node| node.parent == parent)
+            .collect()
+    }
+
+    fn node(&self, id: usize) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Nodes from the root down to `id`.
+    fn path(&self, mut id: Option<usize>) -> Vec<&Node> {
+        let mut path = Vec::new();
+        while let Some(node) = id.and_then(|id| self.node(id)) {
+            path.push(node);
+            id = node.parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Message queued by `/retry`, `/edit` or `/t` to be sent in place of new input.
+pub struct Resend {
+    pub input: String,
+    pub options: RequestOptions,
+    pub run_output: Option<String>,
+    /// Inline command output to reuse instead of running the commands again.
+    pub inline_output: Option<Option<String>>,
+    /// Template body of a `/t` message, see [`Turn::source`].
+    pub source: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(input: &str) -> Turn {
+        Turn {
+            model: String::new(),
+            input: input.to_string(),
+            cleaned: input.to_string(),
+            response: format!("re: {}", input),
+            run_output: None,
+            inline_output: None,
+            time: String::new(),
+            attachments: Vec::new(),
+            source: None,
+        }
+    }
+
+    fn inputs(history: &History) -> Vec<&str> {
+        history
+            .active()
+            .iter()
+            .map(|turn| turn.input.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn resending_after_rewind_adds_a_sibling_branch() {
+        let mut history = History::default();
+        history.push(turn("a"));
+        history.push(turn("b"));
+        assert_eq!(history.rewind().unwrap().input, "b");
+        history.push(turn("b2"));
+
+        assert_eq!(inputs(&history), ["a", "b2"]);
+        assert_eq!(history.tree(), "1 a\n├─ 2 b\n└─ 3* b2");
+        assert_eq!(
+            history.branches(),
+            "    2  (2 turns) b\n*   3  (2 turns) b2"
+        );
+    }
+
+    #[test]
+    fn checking_out_an_inner_exchange_continues_from_it() {
+        let mut history = History::default();
+        history.push(turn("a"));
+        history.push(turn("b"));
+        history.push(turn("c"));
+        assert_eq!(history.checkout(1).unwrap().input, "a");
+        assert_eq!(inputs(&history), ["a"]);
+        history.push(turn("d"));
+
+        assert_eq!(inputs(&history), ["a", "d"]);
+        assert_eq!(history.tree(), "1 a\n├─ 2 b\n│  └─ 3 c\n└─ 4* d");
+        assert!(history.checkout(9).is_err());
+    }
+
+    #[test]
+    fn undo_keeps_exchanges_other_branches_continue_from() {
+        let mut history = History::default();
+        assert!(history.undo().is_none());
+        history.push(turn("a"));
+        history.push(turn("b"));
+        history.checkout(1).unwrap();
+        history.push(turn("c"));
+        history.checkout(1).unwrap();
+
+        // `a` has children, so undoing it only moves the head to the root.
+        assert_eq!(history.undo().unwrap().input, "a");
+        assert!(history.active().is_empty());
+        assert_eq!(history.tree(), "1 a\n├─ 2 b\n└─ 3 c");
+        assert!(history.undo().is_none());
+
+        history.checkout(3).unwrap();
+        history.undo().unwrap();
+        assert_eq!(history.tree(), "1* a\n└─ 2 b");
+    }
+}
@@ -1,18 +1,27 @@
 use rustyline::Editor;
 use rustyline::error::ReadlineError;
 use rustyline::history::DefaultHistory;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use super::commands::CommandCompleter;
+use super::keys;
+use super::registry::Registry;
 
-/// Initializes the line editor with command completion.
-pub fn new_editor() -> Editor<CommandCompleter, DefaultHistory> {
-    let mut rl = Editor::<CommandCompleter, DefaultHistory>::new()
+/// Initializes the line editor with command completion, multi-line input
+/// and the configured edit mode and key bindings.
+pub fn new_editor(registry: &Rc<Registry>) -> Editor<CommandCompleter, DefaultHistory> {
+    let mut rl = Editor::<CommandCompleter, DefaultHistory>::with_config(keys::editor_config())
         .expect("failed to initialize rustyline editor");
-    rl.set_helper(Some(CommandCompleter::new(vec![
-        "/clean", "/trans", "/eval", "/save", "/help", "/stream", "/add",
-    ])));
+    let helper = CommandCompleter::new(registry);
+    keys::bind_keys(&mut rl, &helper.compose_request);
+    rl.set_helper(Some(helper));
     rl
 }
 
@@ -27,45 +36,255 @@ pub fn open_tty_reader(stdin_is_piped: bool) -> Result<Option<BufReader<File>>,
         .map_err(|err| format!("Error: {}", err))
 }
 
-/// Reads one line of user input from TTY or rustyline.
-pub fn read_user_input(
+/// Reads a single answer line without recording it in the input history.
+fn read_answer(
     rl: &mut Editor<CommandCompleter, DefaultHistory>,
     tty_reader: &mut Option<BufReader<File>>,
+    prompt: &str,
+    initial: &str,
 ) -> Result<Option<String>, String> {
     if let Some(reader) = tty_reader.as_mut() {
-        // TTY mode uses a manual prompt to keep colors consistent.
         let mut stdout = std::io::stdout();
+        if !initial.is_empty() {
+            writeln!(stdout, "{}", initial).map_err(|_| "Error writing prompt".to_string())?;
+        }
         stdout
-            .write_all(b"\x1b[36m\xE2\x9E\x9C ")
+            .write_all(prompt.as_bytes())
+            .and_then(|_| stdout.flush())
             .map_err(|_| "Error writing prompt".to_string())?;
-        stdout
-            .flush()
-            .map_err(|_| "Error flushing prompt".to_string())?;
         let mut line = String::new();
-        match reader.read_line(&mut line) {
+        return match reader.read_line(&mut line) {
             Ok(0) => Ok(None),
-            Ok(_) => {
-                stdout
-                    .write_all(b"\x1b[0m")
-                    .map_err(|_| "Error resetting color".to_string())?;
-                Ok(Some(line.trim().to_string()))
-            }
+            Ok(_) => Ok(Some(line.trim().to_string())),
             Err(err) => Err(format!("Error: {}", err)),
+        };
+    }
+
+    match rl.readline_with_initial(prompt, (initial, "")) {
+        Ok(line) => Ok(Some(line.trim().to_string())),
+        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => Ok(None),
+        Err(err) => Err(format!("Error: {:?}", err)),
+    }
+}
+
+/// Shows a shell command and waits for a yes/no answer.
+pub fn confirm_yes_no(
+    rl: &mut Editor<CommandCompleter, DefaultHistory>,
+    tty_reader: &mut Option<BufReader<File>>,
+    command: &str,
+) -> bool {
+    println!("\n\x1b[33m{}\x1b[0m\n", command);
+    match read_answer(rl, tty_reader, "run? [y/N] ", "") {
+        Ok(answer) => matches!(
+            answer.as_deref().map(str::to_lowercase).as_deref(),
+            Some("y") | Some("yes")
+        ),
+        Err(err) => {
+            eprintln!("{}", err);
+            false
         }
-    } else {
-        println!("\x1b[36m");
-        let readline = rl.readline("➜ ");
-        let user_input = match readline {
-            Ok(line) => {
-                // Keep history for Up/Down navigation.
-                rl.add_history_entry(line.as_str())
-                    .map_err(|_| "Error adding history".to_string())?;
-                line.trim().to_string()
+    }
+}
+
+/// Shows a shell command and asks the user to run, edit, or cancel it.
+/// Returns the command to execute, or `None` when cancelled.
+pub fn confirm_command(
+    rl: &mut Editor<CommandCompleter, DefaultHistory>,
+    tty_reader: &mut Option<BufReader<File>>,
+    command: &str,
+) -> Result<Option<String>, String> {
+    let mut command = command.to_string();
+    loop {
+        println!("\n\x1b[33m{}\x1b[0m\n", command);
+        let answer = read_answer(rl, tty_reader, "run? [y/e/N] ", "")?;
+        match answer.as_deref().map(str::to_lowercase).as_deref() {
+            Some("y") | Some("yes") => return Ok(Some(command)),
+            Some("e") | Some("edit") => {
+                // In TTY mode the current command is echoed and a replacement is read.
+                let edited = read_answer(rl, tty_reader, "$ ", &command)?;
+                match edited {
+                    Some(text) if !text.is_empty() => command = text,
+                    Some(_) => {}
+                    None => return Ok(None),
+                }
             }
-            Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => return Ok(None),
-            Err(err) => return Err(format!("Error: {:?}", err)),
-        };
-        println!("\x1b[0m");
-        Ok(Some(user_input))
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Lets the user edit a previous message on the prompt line.
+pub fn edit_line(
+    rl: &mut Editor<CommandCompleter, DefaultHistory>,
+    tty_reader: &mut Option<BufReader<File>>,
+    initial: &str,
+) -> Result<Option<String>, String> {
+    read_answer(rl, tty_reader, "edit> ", initial)
+}
+
+/// Opens text in `$VISUAL` or `$EDITOR` (default `vi`) and returns the saved result.
+pub fn edit_in_editor(initial: &str) -> Result<String, String> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .ok()
+        .filter(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().unwrap_or("vi");
+    let path = create_edit_file(initial).map_err(|err| format!("Error: {}", err))?;
+
+    // The editor needs the terminal even when stdin is piped.
+    let stdin = File::open("/dev/tty")
+        .map(Stdio::from)
+        .unwrap_or_else(|_| Stdio::inherit());
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .stdin(stdin)
+        .status();
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => return Err(format!("{} exited with {}", program, status)),
+        Err(err) => return Err(format!("Error running {}: {}", program, err)),
+    }
+    edited
+        .map(|text| text.trim().to_string())
+        .map_err(|err| format!("Error: {}", err))
+}
+
+/// Creates a new private temp file holding `initial`. The name is never
+/// reused, so an existing file or link at a guessed path is not followed.
+fn create_edit_file(initial: &str) -> std::io::Result<PathBuf> {
+    let mut attempt = 0;
+    loop {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        let path = env::temp_dir().join(format!(
+            "netero-edit-{}-{}-{}.md",
+            std::process::id(),
+            nanos,
+            attempt
+        ));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                if let Err(err) = file.write_all(initial.as_bytes()) {
+                    let _ = fs::remove_file(&path);
+                    return Err(err);
+                }
+                return Ok(path);
+            }
+            Err(err) if err.kind() == ErrorKind::AlreadyExists && attempt < 16 => attempt += 1,
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// True while the input ends with `\` or has an unclosed code fence, so
+/// reading continues on the next line.
+pub fn needs_continuation(input: &str) -> bool {
+    let fences = input
+        .lines()
+        .filter(|line| line.trim_start().starts_with("```"))
+        .count();
+    input.ends_with('\\') || fences % 2 == 1
+}
+
+/// Drops the `\` of continued lines, keeping the line breaks.
+fn join_continued_lines(input: &str) -> String {
+    input.replace("\\\r\n", "\n").replace("\\\n", "\n")
+}
+
+/// Opens the message in `$EDITOR`, returning an empty message when cancelled.
+fn compose(initial: &str) -> Result<Option<String>, String> {
+    match edit_in_editor(initial) {
+        Ok(text) => {
+            if !text.is_empty() {
+                println!("{}", text);
+            }
+            Ok(Some(text))
+        }
+        Err(err) => {
+            eprintln!("\n{}", err);
+            Ok(Some(String::new()))
+        }
+    }
+}
+
+/// Reads user input from TTY or rustyline, showing the active model in the
+/// prompt marker. Lines continue after a trailing `\` or inside a code fence.
+pub fn read_user_input(
+    rl: &mut Editor<CommandCompleter, DefaultHistory>,
+    tty_reader: &mut Option<BufReader<File>>,
+    model: &str,
+) -> Result<Option<String>, String> {
+    let marker = format!("{} ➜ ", model);
+    match tty_reader.as_mut() {
+        Some(reader) => read_tty_input(reader, &marker),
+        None => read_editor_input(rl, &marker),
     }
 }
+
+fn read_tty_input(reader: &mut BufReader<File>, marker: &str) -> Result<Option<String>, String> {
+    // TTY mode uses a manual prompt to keep colors consistent.
+    let mut stdout = std::io::stdout();
+    let mut prompt = format!("\x1b[36m{}", marker);
+    let mut input = String::new();
+    loop {
+        stdout
+            .write_all(prompt.as_bytes())
+            .and_then(|_| stdout.flush())
+            .map_err(|_| "Error writing prompt".to_string())?;
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) if input.is_empty() => return Ok(None),
+            Ok(0) => break,
+            Ok(_) => input.push_str(&line),
+            Err(err) => return Err(format!("Error: {}", err)),
+        }
+        if !needs_continuation(input.trim_end_matches(['\r', '\n'])) {
+            break;
+        }
+        prompt = "... ".to_string();
+    }
+    stdout
+        .write_all(b"\x1b[0m")
+        .map_err(|_| "Error resetting color".to_string())?;
+    Ok(Some(join_continued_lines(input.trim())))
+}
+
+fn read_editor_input(
+    rl: &mut Editor<CommandCompleter, DefaultHistory>,
+    marker: &str,
+) -> Result<Option<String>, String> {
+    println!("\x1b[36m");
+    let user_input = match rl.readline(marker) {
+        Ok(line) => {
+            // Keep history for Up/Down navigation.
+            rl.add_history_entry(line.as_str())
+                .map_err(|_| "Error adding history".to_string())?;
+            join_continued_lines(line.trim())
+        }
+        Err(ReadlineError::Interrupted) => {
+            let request = rl
+                .helper()
+                .and_then(|helper| helper.compose_request.lock().ok()?.take());
+            println!("\x1b[0m");
+            return match request {
+                Some(line) => compose(&line),
+                None => Ok(None),
+            };
+        }
+        Err(ReadlineError::Eof) => return Ok(None),
+        Err(err) => return Err(format!("Error: {:?}", err)),
+    };
+    println!("\x1b[0m");
+    Ok(Some(user_input))
+}
@@ -0,0 +1,145 @@
+//! Edit mode and key bindings of the chat line editor.
+use rustyline::config::EditMode;
+use rustyline::history::DefaultHistory;
+use rustyline::{
+    Cmd, ConditionalEventHandler, Config, Editor, Event, EventContext, EventHandler, KeyCode,
+    KeyEvent, Modifiers, Movement, RepeatCount,
+};
+use std::env;
+use std::sync::{Arc, Mutex};
+
+use super::commands::CommandCompleter;
+
+/// Binding of the `editor` action unless `NETERO_KEYBINDINGS` says otherwise.
+const DEFAULT_BINDINGS: &str = "ctrl-x ctrl-e=editor";
+
+/// Editor settings: `NETERO_EDIT_MODE` selects `emacs` (default) or `vi`.
+pub fn editor_config() -> Config {
+    let edit_mode = match env::var("NETERO_EDIT_MODE").as_deref().map(str::trim) {
+        Ok("vi") => EditMode::Vi,
+        Ok("emacs") | Ok("") | Err(_) => EditMode::Emacs,
+        Ok(other) => {
+            eprintln!("unknown NETERO_EDIT_MODE '{}', using emacs", other);
+            EditMode::Emacs
+        }
+    };
+    Config::builder()
+        .edit_mode(edit_mode)
+        // Pasted text is inserted as-is, newlines included.
+        .bracketed_paste(true)
+        .build()
+}
+
+/// Binds the default keys and those of `NETERO_KEYBINDINGS`, a comma-separated
+/// list of `keys=action` such as `alt-enter=newline, ctrl-x ctrl-e=editor`.
+/// Invalid entries are reported and skipped.
+pub fn bind_keys(
+    rl: &mut Editor<CommandCompleter, DefaultHistory>,
+    compose_request: &Arc<Mutex<Option<String>>>,
+) {
+    let custom = env::var("NETERO_KEYBINDINGS").unwrap_or_default();
+    for entry in DEFAULT_BINDINGS.split(',').chain(custom.split(',')) {
+        if entry.trim().is_empty() {
+            continue;
+        }
+        match parse_binding(entry, compose_request) {
+            Ok((keys, handler)) => {
+                rl.bind_sequence(keys, handler);
+            }
+            Err(err) => eprintln!("NETERO_KEYBINDINGS: {}", err),
+        }
+    }
+}
+
+fn parse_binding(
+    entry: &str,
+    compose_request: &Arc<Mutex<Option<String>>>,
+) -> Result<(Event, EventHandler), String> {
+    let (keys, action) = entry
+        .split_once('=')
+        .ok_or_else(|| format!("invalid binding '{}', expected KEYS=ACTION", entry.trim()))?;
+    let keys = keys
+        .split_whitespace()
+        .map(parse_key)
+        .collect::<Result<Vec<_>, _>>()?;
+    if keys.is_empty() {
+        return Err(format!("no keys in '{}'", entry.trim()));
+    }
+    let handler = match action.trim() {
+        "editor" => EventHandler::Conditional(Box::new(ComposeShortcut(compose_request.clone()))),
+        "newline" => Cmd::Newline.into(),
+        "accept" => Cmd::AcceptLine.into(),
+        "accept-hint" => Cmd::CompleteHint.into(),
+        "complete" => Cmd::Complete.into(),
+        "history-search-backward" => Cmd::HistorySearchBackward.into(),
+        "history-search-forward" => Cmd::HistorySearchForward.into(),
+        "clear-screen" => Cmd::ClearScreen.into(),
+        "kill-line" => Cmd::Kill(Movement::EndOfLine).into(),
+        "undo" => Cmd::Undo(1).into(),
+        "none" => Cmd::Noop.into(),
+        other => return Err(format!("unknown action '{}'", other)),
+    };
+    Ok((Event::KeySeq(keys), handler))
+}
+
+/// Parses a key such as `ctrl-x`, `alt-enter`, `shift-tab`, `f5` or `a`.
+fn parse_key(spec: &str) -> Result<KeyEvent, String> {
+    let mut mods = Modifiers::NONE;
+    let mut rest = spec;
+    loop {
+        let lower = rest.to_ascii_lowercase();
+        let (modifier, len) = if lower.starts_with("ctrl-") {
+            (Modifiers::CTRL, 5)
+        } else if lower.starts_with("alt-") {
+            (Modifiers::ALT, 4)
+        } else if lower.starts_with("shift-") {
+            (Modifiers::SHIFT, 6)
+        } else {
+            break;
+        };
+        mods |= modifier;
+        rest = &rest[len..];
+    }
+
+    let code = match rest.to_ascii_lowercase().as_str() {
+        "enter" | "return" => KeyCode::Enter,
+        "tab" if mods.contains(Modifiers::SHIFT) => {
+            mods.remove(Modifiers::SHIFT);
+            KeyCode::BackTab
+        }
+        "tab" => KeyCode::Tab,
+        "esc" | "escape" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pageup" => KeyCode::PageUp,
+        "pagedown" => KeyCode::PageDown,
+        name => match (name.strip_prefix('f'), rest.chars().count()) {
+            (_, 1) => return Ok(KeyEvent::new(rest.chars().next().unwrap_or(' '), mods)),
+            (Some(num), _) if num.parse::<u8>().is_ok_and(|n| (1..=24).contains(&n)) => {
+                KeyCode::F(num.parse().unwrap_or(1))
+            }
+            _ => return Err(format!("unknown key '{}'", spec)),
+        },
+    };
+    Ok(KeyEvent(code, mods))
+}
+
+/// Records the current line and leaves readline so the message can be
+/// composed in `$EDITOR`.
+struct ComposeShortcut(Arc<Mutex<Option<String>>>);
+
+impl ConditionalEventHandler for ComposeShortcut {
+    fn handle(&self, _: &Event, _: RepeatCount, _: bool, ctx: &EventContext) -> Option<Cmd> {
+        if let Ok(mut request) = self.0.lock() {
+            *request = Some(ctx.line().to_string());
+        }
+        Some(Cmd::Interrupt)
+    }
+}
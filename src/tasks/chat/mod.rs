@@ -1,12 +1,21 @@
 //! Chat task implementation and helpers.
 mod commands;
+mod complete;
 mod eval;
-mod inline_exec;
+mod export;
+mod files;
+mod highlight;
+mod history;
 mod input;
+mod keys;
 mod lang;
 mod parse;
+mod persona;
 mod prompt;
+mod registry;
+mod session;
 mod stream;
 pub mod task;
 
+pub use export::export_session;
 pub use task::generate_chat as connect;
@@ -0,0 +1,93 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::utils;
+
+/// Role of the built-in persona.
+const DEFAULT_ROLE: &str = "Conversational terminal assistant";
+/// Instructions of the built-in persona.
+const DEFAULT_INSTRUCTIONS: &str = "- Keep responses concise: 5-20 lines maximum.\n\
+- Do not use emojis or decorations.";
+
+/// Role and instructions the chat model follows, with optional request defaults.
+pub struct Persona {
+    pub name: String,
+    pub role: String,
+    pub instructions: String,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+}
+
+impl Persona {
+    /// Persona used when no other is selected.
+    pub fn builtin() -> Self {
+        Self {
+            name: "default".to_string(),
+            role: DEFAULT_ROLE.to_string(),
+            instructions: DEFAULT_INSTRUCTIONS.to_string(),
+            model: None,
+            temperature: None,
+        }
+    }
+
+    /// Loads `personas/<name>.md` from the config directory. `default` falls
+    /// back to the built-in persona when there is no such file.
+    pub fn load(name: &str) -> Result<Self, String> {
+        let path = personas_dir().map(|dir| dir.join(format!("{}.md", name)));
+        let text = match path.as_ref().map(fs::read_to_string) {
+            Some(Ok(text)) => text,
+            _ if name == "default" => return Ok(Self::builtin()),
+            Some(Err(err)) if err.kind() == ErrorKind::NotFound => {
+                return Err(format!("persona '{}' not found", name));
+            }
+            Some(Err(err)) => return Err(format!("persona '{}': {}", name, err)),
+            None => return Err("no config directory".to_string()),
+        };
+
+        let (fields, body) = utils::split_front_matter(&text);
+        let mut persona = Self {
+            name: name.to_string(),
+            role: DEFAULT_ROLE.to_string(),
+            instructions: body.trim().to_string(),
+            model: None,
+            temperature: None,
+        };
+        for (key, value) in fields {
+            match key.as_str() {
+                "role" => persona.role = value,
+                "model" if !value.is_empty() => persona.model = Some(value),
+                "temperature" => {
+                    let temperature = value
+                        .parse::<f32>()
+                        .map_err(|_| format!("persona '{}': invalid temperature", name))?;
+                    persona.temperature = Some(temperature);
+                }
+                _ => {}
+            }
+        }
+        Ok(persona)
+    }
+}
+
+/// Names of the available personas, including `default`.
+pub fn list_personas() -> Vec<String> {
+    let mut names = vec!["default".to_string()];
+    let Some(entries) = personas_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return names;
+    };
+    names.extend(
+        entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string())),
+    );
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn personas_dir() -> Option<PathBuf> {
+    utils::config_dir().map(|dir| dir.join("personas"))
+}
@@ -1,8 +1,11 @@
 /// Builds the chat prompt body from already-resolved user, datetime, history, and input values.
+#[allow(clippy::too_many_arguments)]
 pub fn create_prompt(
     username: &str,
     datetime: &str,
     user_lang: &str,
+    role: &str,
+    instructions: &str,
     history: &str,
     user_input: &str,
     command_output: Option<&str>,
@@ -37,10 +40,9 @@ pub fn create_prompt(
     };
 
     format!(
-        "LLM ROL: Conversational terminal assistant\nUSERNAME: {}\nDATETIME: {}\nUSER LANG: {}\n\
+        "LLM ROL: {}\nUSERNAME: {}\nDATETIME: {}\nUSER LANG: {}\n\
 :: INSTRUCTION (SYSTEM) ::\n\
-- Keep responses concise: 5-20 lines maximum.\n\
-- Do not use emojis or decorations.\n\
+{}\n\
 - Always prioritize the latest user message over the HISTORICAL CHAT.\n\
 - The latest message may be completely unrelated to previous messages.\n\
 - Do not assume continuity or context from the history unless the user explicitly refers to it.\n\
@@ -52,6 +54,6 @@ pub fn create_prompt(
 :: USER MESSAGE ::\n\
 {}\n\
 :: END USER MESSAGE ::",
-        username, datetime, user_lang, history, extra_block, user_input
+        role, username, datetime, user_lang, instructions, history, extra_block, user_input
     )
 }
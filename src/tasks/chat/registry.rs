@@ -0,0 +1,591 @@
+//! Slash commands of the chat. One table drives dispatch, completion, hints
+//! and `/help`, together with user aliases from the config directory.
+use futures_util::future::LocalBoxFuture;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::core;
+use crate::tasks::inline::{InlineCommand, run_commands};
+use crate::tasks::template;
+use crate::utils;
+
+use super::commands::{
+    add_pending_output, handle_add, handle_branches, handle_checkout, handle_clean, handle_code,
+    handle_copy, handle_drop, handle_edit, handle_editor, handle_eval, handle_export, handle_files,
+    handle_model, handle_persona, handle_pin, handle_profile, handle_refresh, handle_retry,
+    handle_run, handle_save, handle_stream, handle_system, handle_template, handle_trans,
+    handle_tree, handle_undo,
+};
+use super::history::Resend;
+use super::input::confirm_yes_no;
+use super::persona::list_personas;
+use super::task::Chat;
+
+/// Code run for a command, given the text after its name. An error from an
+/// async handler ends the chat.
+pub enum Handler {
+    Sync(fn(&mut Chat, &str)),
+    Async(for<'c, 'a> fn(&'c mut Chat<'a>, &'c str) -> LocalBoxFuture<'c, Result<(), String>>),
+}
+
+/// Completion offered for the arguments of a command.
+pub enum Completion {
+    None,
+    Paths,
+    /// Names of the slash commands.
+    Commands,
+    Values(fn() -> Vec<String>),
+}
+
+/// Built-in slash command.
+pub struct Command {
+    pub name: &'static str,
+    pub aliases: &'static [&'static str],
+    /// Arguments, shown in `/help` and as a hint while typing.
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub complete: Completion,
+    pub run: Handler,
+}
+
+/// User command from `aliases/<name>.md` in the config directory. The body is
+/// the text the command expands to, with `{{args}}` replaced by its arguments
+/// (appended when there is no placeholder). A body starting with `/` runs
+/// that command, one starting with `!` runs a shell snippet and anything else
+/// is sent as a message.
+pub struct Alias {
+    pub name: String,
+    pub description: String,
+    pub expansion: String,
+}
+
+impl Alias {
+    fn usage(&self) -> &'static str {
+        if self.expansion.contains("{{args}}") {
+            "<args>"
+        } else {
+            "[args]"
+        }
+    }
+
+    fn expand(&self, args: &str) -> String {
+        let args = args.trim();
+        if self.expansion.contains("{{args}}") {
+            self.expansion.replace("{{args}}", args)
+        } else if args.is_empty() {
+            self.expansion.clone()
+        } else {
+            format!("{} {}", self.expansion, args)
+        }
+    }
+}
+
+pub struct Registry {
+    commands: Vec<Command>,
+    aliases: Vec<Alias>,
+}
+
+impl Registry {
+    /// Built-in commands plus the user aliases that do not shadow one of them.
+    pub fn load() -> Self {
+        let mut registry = Self {
+            commands: builtin(),
+            aliases: Vec::new(),
+        };
+        for alias in load_aliases() {
+            if registry.command(&alias.name).is_some() {
+                eprintln!("alias {} ignored: it is a built-in command", alias.name);
+            } else {
+                registry.aliases.push(alias);
+            }
+        }
+        registry
+    }
+
+    /// Every name a command can be called by, user aliases included.
+    pub fn names(&self) -> Vec<String> {
+        self.usages().map(|(name, _)| name.to_string()).collect()
+    }
+
+    /// Built-in command called `name` or one of its aliases.
+    pub fn command(&self, name: &str) -> Option<&Command> {
+        self.commands
+            .iter()
+            .find(|cmd| cmd.name == name || cmd.aliases.contains(&name))
+    }
+
+    fn alias(&self, name: &str) -> Option<&Alias> {
+        self.aliases.iter().find(|alias| alias.name == name)
+    }
+
+    pub fn completion(&self, name: &str) -> Option<&Completion> {
+        self.command(name).map(|cmd| &cmd.complete)
+    }
+
+    /// Names and usages of all commands and aliases.
+    fn usages(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.commands
+            .iter()
+            .flat_map(|cmd| {
+                std::iter::once(cmd.name)
+                    .chain(cmd.aliases.iter().copied())
+                    .map(|name| (name, cmd.usage))
+            })
+            .chain(
+                self.aliases
+                    .iter()
+                    .map(|alias| (alias.name.as_str(), alias.usage())),
+            )
+    }
+
+    /// Usage hint for the command being typed: the rest of its name when the
+    /// prefix matches a single command, then its arguments until the first
+    /// one is typed.
+    pub fn hint(&self, line: &str) -> Option<String> {
+        let name_end = line.find(' ').unwrap_or(line.len());
+        let (name, rest) = line.split_at(name_end);
+        if !name.starts_with('/') || !rest.trim().is_empty() {
+            return None;
+        }
+        let (cmd, usage) = match self.usages().find(|(cmd, _)| *cmd == name) {
+            Some(exact) => exact,
+            None if rest.is_empty() => {
+                let mut matches = self.usages().filter(|(cmd, _)| cmd.starts_with(name));
+                match (matches.next(), matches.next()) {
+                    (Some(only), None) => only,
+                    _ => return None,
+                }
+            }
+            None => return None,
+        };
+        let mut hint = cmd[name.len()..].to_string();
+        if !usage.is_empty() {
+            if !line.ends_with(' ') {
+                hint.push(' ');
+            }
+            hint.push_str(usage);
+        }
+        (!hint.is_empty()).then_some(hint)
+    }
+
+    /// Help for one command, or the list of all commands and aliases.
+    pub fn help(&self, topic: &str) -> String {
+        let topic = topic.trim();
+        if topic.is_empty() {
+            return self.overview();
+        }
+        let name = if topic.starts_with('/') {
+            topic.to_string()
+        } else {
+            format!("/{}", topic)
+        };
+        if let Some(cmd) = self.command(&name) {
+            let mut text = format!("Usage: {} {}", cmd.name, cmd.usage)
+                .trim_end()
+                .to_string();
+            if !cmd.aliases.is_empty() {
+                text.push_str(&format!("\nAliases: {}", cmd.aliases.join(", ")));
+            }
+            text.push_str(&format!("\n{}", cmd.help));
+            return text;
+        }
+        if let Some(alias) = self.alias(&name) {
+            let mut text = format!(
+                "Usage: {} {}\nExpands to: {}",
+                alias.name,
+                alias.usage(),
+                alias.expansion
+            );
+            if !alias.description.is_empty() {
+                text.push_str(&format!("\n{}", alias.description));
+            }
+            return text;
+        }
+        format!("unknown command: {}", name)
+    }
+
+    fn overview(&self) -> String {
+        let entries: Vec<(String, &str)> = self
+            .commands
+            .iter()
+            .map(|cmd| (format!("{} {}", cmd.name, cmd.usage), cmd.help))
+            .collect();
+        let aliases: Vec<(String, &str)> = self
+            .aliases
+            .iter()
+            .map(|alias| {
+                let help = if alias.description.is_empty() {
+                    alias.expansion.as_str()
+                } else {
+                    alias.description.as_str()
+                };
+                (format!("{} {}", alias.name, alias.usage()), help)
+            })
+            .collect();
+        let width = entries
+            .iter()
+            .chain(&aliases)
+            .map(|(usage, _)| usage.chars().count())
+            .max()
+            .unwrap_or(0);
+        let list = |entries: &[(String, &str)]| {
+            entries
+                .iter()
+                .map(|(usage, help)| format!("{:width$}  {}", usage, help, width = width))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut text = format!("\nCommands:\n{}", list(&entries));
+        if !aliases.is_empty() {
+            text.push_str(&format!("\n\nAliases:\n{}", list(&aliases)));
+        }
+        text.push_str("\n\n/help <command> shows the details of a command.");
+        text
+    }
+
+    /// Runs the slash command or alias at the start of the input. Returns
+    /// false when the input is not one, so it is sent as a message.
+    pub async fn dispatch(&self, chat: &mut Chat<'_>, input: &str) -> Result<bool, String> {
+        let (name, args) = split_command(input);
+        let Some(alias) = self.alias(name) else {
+            return self.run(chat, input).await;
+        };
+
+        let expanded = alias.expand(args);
+        if let Some(snippet) = expanded.strip_prefix('!') {
+            run_snippet(chat, snippet);
+            return Ok(true);
+        }
+        let (target, _) = split_command(&expanded);
+        if self.alias(target).is_some() {
+            eprintln!("\n{} expands to another alias, {}", alias.name, target);
+            return Ok(true);
+        }
+        if !self.run(chat, &expanded).await? {
+            chat.resend = Some(Resend {
+                input: expanded,
+                options: core::RequestOptions::default(),
+                run_output: chat.pending_output.take(),
+                inline_output: None,
+                source: None,
+            });
+        }
+        Ok(true)
+    }
+
+    async fn run(&self, chat: &mut Chat<'_>, input: &str) -> Result<bool, String> {
+        let (name, args) = split_command(input);
+        let Some(cmd) = self.command(name) else {
+            return Ok(false);
+        };
+        match cmd.run {
+            Handler::Sync(handler) => handler(chat, args),
+            Handler::Async(handler) => handler(chat, args).await?,
+        }
+        Ok(true)
+    }
+}
+
+/// Splits input into its first word and the rest.
+fn split_command(input: &str) -> (&str, &str) {
+    input.split_once(char::is_whitespace).unwrap_or((input, ""))
+}
+
+/// Runs a shell snippet of an alias like `/run`, attaching its output to the
+/// next message.
+fn run_snippet(chat: &mut Chat, snippet: &str) {
+    let command = InlineCommand {
+        command: snippet.trim().to_string(),
+        sandboxed: false,
+    };
+    let output = run_commands(&[command], &chat.policy, &mut |cmd| {
+        confirm_yes_no(&mut chat.rl, &mut chat.tty_reader, cmd)
+    });
+    if let Some(output) = output {
+        println!("\n{}", output);
+        add_pending_output(&mut chat.pending_output, output);
+    }
+}
+
+/// Aliases in the config directory, sorted by name.
+fn load_aliases() -> Vec<Alias> {
+    let Some(entries) = aliases_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut aliases: Vec<Alias> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_str()?.to_string();
+            let text = fs::read_to_string(&path).ok()?;
+            let (fields, body) = utils::split_front_matter(&text);
+            let description = fields
+                .into_iter()
+                .find(|(key, _)| key == "description")
+                .map(|(_, value)| value)
+                .unwrap_or_default();
+            Some(Alias {
+                name: format!("/{}", name),
+                description,
+                expansion: body.trim().to_string(),
+            })
+        })
+        .filter(|alias| !alias.expansion.is_empty())
+        .collect();
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+    aliases
+}
+
+fn aliases_dir() -> Option<PathBuf> {
+    utils::config_dir().map(|dir| dir.join("aliases"))
+}
+
+fn help(chat: &mut Chat, args: &str) {
+    println!("{}", chat.registry.help(args));
+}
+
+fn trans<'c>(chat: &'c mut Chat, args: &'c str) -> LocalBoxFuture<'c, Result<(), String>> {
+    Box::pin(handle_trans(chat, args))
+}
+
+fn save<'c>(chat: &'c mut Chat, args: &'c str) -> LocalBoxFuture<'c, Result<(), String>> {
+    Box::pin(handle_save(chat, args))
+}
+
+fn template_names() -> Vec<String> {
+    template::all_templates()
+        .into_iter()
+        .map(|template| template.name)
+        .collect()
+}
+
+fn profile_names() -> Vec<String> {
+    let mut names = vec!["default".to_string()];
+    names.extend(core::Config::profile_names());
+    names
+}
+
+/// The built-in commands, in the order `/help` lists them.
+fn builtin() -> Vec<Command> {
+    vec![
+        Command {
+            name: "/help",
+            aliases: &["/?"],
+            usage: "[command]",
+            help: "Show this help message, or the details of a command",
+            complete: Completion::Commands,
+            run: Handler::Sync(help),
+        },
+        Command {
+            name: "/editor",
+            aliases: &[],
+            usage: "[text]",
+            help: "Compose the message in $EDITOR (also Ctrl-X Ctrl-E)",
+            complete: Completion::None,
+            run: Handler::Sync(handle_editor),
+        },
+        Command {
+            name: "/clean",
+            aliases: &["/clear"],
+            usage: "",
+            help: "Clear the screen and start a new conversation",
+            complete: Completion::None,
+            run: Handler::Sync(handle_clean),
+        },
+        Command {
+            name: "/model",
+            aliases: &[],
+            usage: "[name]",
+            help: "Show or change the model",
+            complete: Completion::None,
+            run: Handler::Sync(handle_model),
+        },
+        Command {
+            name: "/profile",
+            aliases: &[],
+            usage: "<name|default>",
+            help: "Switch to a backend profile",
+            complete: Completion::Values(profile_names),
+            run: Handler::Sync(handle_profile),
+        },
+        Command {
+            name: "/system",
+            aliases: &[],
+            usage: "[text|@file|reset]",
+            help: "Show or replace the system instructions",
+            complete: Completion::Values(|| vec!["reset".to_string()]),
+            run: Handler::Sync(handle_system),
+        },
+        Command {
+            name: "/persona",
+            aliases: &[],
+            usage: "[name]",
+            help: "List personas or switch to one",
+            complete: Completion::Values(list_personas),
+            run: Handler::Sync(handle_persona),
+        },
+        Command {
+            name: "/t",
+            aliases: &["/template"],
+            usage: "<template> [args]",
+            help: "Send a prompt template (/t lists them)",
+            complete: Completion::Values(template_names),
+            run: Handler::Sync(handle_template),
+        },
+        Command {
+            name: "/add",
+            aliases: &[],
+            usage: "<path>...",
+            help: "Attach files to the chat, sent with every message",
+            complete: Completion::Paths,
+            run: Handler::Sync(handle_add),
+        },
+        Command {
+            name: "/files",
+            aliases: &[],
+            usage: "",
+            help: "List attached files with their sizes",
+            complete: Completion::None,
+            run: Handler::Sync(handle_files),
+        },
+        Command {
+            name: "/drop",
+            aliases: &[],
+            usage: "<path|n>...",
+            help: "Remove an attached file",
+            complete: Completion::Paths,
+            run: Handler::Sync(handle_drop),
+        },
+        Command {
+            name: "/pin",
+            aliases: &[],
+            usage: "<path|n>...",
+            help: "Attach a file and re-read it whenever it changes",
+            complete: Completion::Paths,
+            run: Handler::Sync(handle_pin),
+        },
+        Command {
+            name: "/refresh",
+            aliases: &[],
+            usage: "",
+            help: "Re-read all attached files from disk",
+            complete: Completion::None,
+            run: Handler::Sync(handle_refresh),
+        },
+        Command {
+            name: "/retry",
+            aliases: &[],
+            usage: "[model] [temperature]",
+            help: "Regenerate the last answer",
+            complete: Completion::None,
+            run: Handler::Sync(handle_retry),
+        },
+        Command {
+            name: "/undo",
+            aliases: &[],
+            usage: "",
+            help: "Remove the last exchange",
+            complete: Completion::None,
+            run: Handler::Sync(handle_undo),
+        },
+        Command {
+            name: "/edit",
+            aliases: &[],
+            usage: "[editor]",
+            help: "Edit the last message and send it again",
+            complete: Completion::Values(|| vec!["editor".to_string()]),
+            run: Handler::Sync(handle_edit),
+        },
+        Command {
+            name: "/branches",
+            aliases: &[],
+            usage: "",
+            help: "List conversation branches",
+            complete: Completion::None,
+            run: Handler::Sync(handle_branches),
+        },
+        Command {
+            name: "/checkout",
+            aliases: &["/co"],
+            usage: "<id>",
+            help: "Continue from an exchange of another branch",
+            complete: Completion::None,
+            run: Handler::Sync(handle_checkout),
+        },
+        Command {
+            name: "/tree",
+            aliases: &[],
+            usage: "",
+            help: "Show the conversation tree",
+            complete: Completion::None,
+            run: Handler::Sync(handle_tree),
+        },
+        Command {
+            name: "/trans",
+            aliases: &[],
+            usage: "[IN:OUT] <text>",
+            help: "Translate text (uses LLM)",
+            complete: Completion::None,
+            run: Handler::Async(trans),
+        },
+        Command {
+            name: "/eval",
+            aliases: &[],
+            usage: "<expression>",
+            help: "Evaluate arithmetic expression",
+            complete: Completion::None,
+            run: Handler::Sync(handle_eval),
+        },
+        Command {
+            name: "/save",
+            aliases: &[],
+            usage: "[hint]",
+            help: "Save an informe about the chat",
+            complete: Completion::None,
+            run: Handler::Async(save),
+        },
+        Command {
+            name: "/export",
+            aliases: &[],
+            usage: "[md|json|html] [path]",
+            help: "Write the conversation as it happened to a file",
+            complete: Completion::Values(|| {
+                vec!["md".to_string(), "json".to_string(), "html".to_string()]
+            }),
+            run: Handler::Sync(handle_export),
+        },
+        Command {
+            name: "/stream",
+            aliases: &[],
+            usage: "[on|off]",
+            help: "Toggle streaming output",
+            complete: Completion::Values(|| vec!["on".to_string(), "off".to_string()]),
+            run: Handler::Sync(handle_stream),
+        },
+        Command {
+            name: "/code",
+            aliases: &[],
+            usage: "[N|lang] [path]",
+            help: "Print or write a code block of the last answer",
+            complete: Completion::None,
+            run: Handler::Sync(handle_code),
+        },
+        Command {
+            name: "/copy",
+            aliases: &[],
+            usage: "[code [N|lang]]",
+            help: "Copy the last answer or a code block to the clipboard",
+            complete: Completion::Values(|| vec!["code".to_string()]),
+            run: Handler::Sync(handle_copy),
+        },
+        Command {
+            name: "/run",
+            aliases: &[],
+            usage: "[N]",
+            help: "Run a shell code block of the last answer after confirmation",
+            complete: Completion::None,
+            run: Handler::Sync(handle_run),
+        },
+    ]
+}
@@ -0,0 +1,170 @@
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::fs::{self, DirBuilder, OpenOptions, Permissions};
+use std::io::Write;
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::core::Redactor;
+
+use super::history::History;
+
+/// Chat saved to disk after every exchange so it can be resumed later.
+#[derive(Serialize, Deserialize)]
+pub struct Session {
+    pub id: String,
+    pub created: String,
+    /// Persona selected with `--persona` or `/persona`.
+    #[serde(default)]
+    pub persona: Option<String>,
+    /// Instructions set with `/system`, replacing the persona's ones.
+    #[serde(default)]
+    pub system: Option<String>,
+    pub history: History,
+}
+
+impl Session {
+    pub fn new() -> Self {
+        let now = Local::now();
+        Self {
+            // The pid keeps chats started in the same second apart.
+            id: format!("{}-{}", now.format("%Y%m%d-%H%M%S"), process::id()),
+            created: now.format("%Y-%m-%d %H:%M:%S").to_string(),
+            persona: None,
+            system: None,
+            history: History::default(),
+        }
+    }
+
+    /// Loads a session by id, or the most recently saved one for `last`.
+    pub fn load(id: &str) -> Result<Self, String> {
+        let dir = sessions_dir().ok_or_else(|| "no session directory".to_string())?;
+        let path = if id == "last" {
+            latest_session(&dir).ok_or_else(|| "no saved sessions".to_string())?
+        } else if is_valid_id(id) {
+            dir.join(format!("{}.json", id))
+        } else {
+            return Err(format!("invalid session id '{}'", id));
+        };
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("session {}: {}", path.display(), err))?;
+        let session: Self = serde_json::from_str(&text)
+            .map_err(|err| format!("session {}: {}", path.display(), err))?;
+        // The id names the file the session is saved to and exported as.
+        if !is_valid_id(&session.id) {
+            return Err(format!(
+                "session {}: invalid id '{}'",
+                path.display(),
+                session.id
+            ));
+        }
+        Ok(session)
+    }
+
+    /// Writes the session, once it has at least one exchange, with the
+    /// secrets `redactor` finds replaced. Sessions hold command output and
+    /// file contents, so only the owner can read them.
+    pub fn save(&self, redactor: Option<&Redactor>) -> Result<(), String> {
+        if self.history.is_empty() {
+            return Ok(());
+        }
+        let dir = sessions_dir().ok_or_else(|| "no session directory".to_string())?;
+        DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(&dir)
+            .map_err(|err| err.to_string())?;
+        fs::set_permissions(&dir, Permissions::from_mode(0o700)).map_err(|err| err.to_string())?;
+        let mut value = serde_json::to_value(self).map_err(|err| err.to_string())?;
+        if let Some(redactor) = redactor {
+            redact_strings(&mut value, redactor);
+        }
+        let text = serde_json::to_string_pretty(&value).map_err(|err| err.to_string())?;
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(dir.join(format!("{}.json", self.id)))
+            .map_err(|err| err.to_string())?;
+        // Files saved by older versions keep their mode until rewritten here.
+        file.set_permissions(Permissions::from_mode(0o600))
+            .map_err(|err| err.to_string())?;
+        file.write_all(text.as_bytes())
+            .map_err(|err| err.to_string())
+    }
+}
+
+/// True for ids in the generated `YYYYMMDD-HHMMSS[-PID]` form, so an id can
+/// never name a path outside the session directory.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.starts_with(|ch: char| ch.is_ascii_digit())
+        && id.chars().all(|ch| ch.is_ascii_digit() || ch == '-')
+}
+
+/// False when `NETERO_SESSIONS` is `off`, `0`, `false` or `no`.
+pub fn saving_enabled() -> bool {
+    !env::var("NETERO_SESSIONS").is_ok_and(|v| matches!(v.trim(), "0" | "false" | "off" | "no"))
+}
+
+/// Redacts every string of the serialized session: messages, command output
+/// and attached file contents alike.
+fn redact_strings(value: &mut Value, redactor: &Redactor) {
+    match value {
+        Value::String(text) => *text = redactor.redact(text).0,
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| redact_strings(item, redactor)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|field| redact_strings(field, redactor)),
+        _ => {}
+    }
+}
+
+/// `NETERO_SESSION_DIR`, or `netero/sessions` under the XDG data directory.
+pub fn sessions_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("NETERO_SESSION_DIR")
+        && !dir.trim().is_empty()
+    {
+        return Some(PathBuf::from(dir));
+    }
+    let data = env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".local/share"))
+        })?;
+    Some(data.join("netero").join("sessions"))
+}
+
+fn latest_session(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_generated_ids_are_valid() {
+        assert!(is_valid_id(&Session::new().id));
+        assert!(is_valid_id("20260101-120000"));
+        for id in ["", "../../x", "a/b", "..\\x", "..", "-1", "last.json", "x"] {
+            assert!(!is_valid_id(id), "{}", id);
+        }
+    }
+}
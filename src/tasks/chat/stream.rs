@@ -1,5 +1,4 @@
 use futures_util::StreamExt;
-use serde_json::json;
 use std::io::Write;
 
 use crate::core;
@@ -8,14 +7,11 @@ use crate::core;
 pub async fn stream_completion(
     service: &core::Service,
     prompt: &str,
+    images: &[core::Image],
+    options: &core::RequestOptions,
 ) -> Result<String, Box<dyn std::error::Error>> {
-    let body = json!({
-        "model": service.model,
-        "messages": [
-            { "role": "user", "content": prompt }
-        ],
-        "stream": true
-    });
+    let mut body = service.chat_request(prompt, images, options)?;
+    body.stream = true;
 
     let mut req = service.http.post(&service.endpoint).json(&body);
 
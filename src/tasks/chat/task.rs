@@ -1,101 +1,223 @@
+use rustyline::Editor;
+use rustyline::history::DefaultHistory;
+use std::fs::File;
+use std::io::BufReader;
+use std::rc::Rc;
+
 use crate::core;
 use crate::tasks::attach;
+use crate::tasks::inline::{self, ExecPolicy};
 use crate::tasks::render;
 use crate::utils;
 
-use super::commands::{
-    handle_add, handle_clean, handle_eval, handle_help, handle_save, handle_stream, handle_trans,
-};
-use super::inline_exec::run_inline_commands;
-use super::input::{new_editor, open_tty_reader, read_user_input};
-use super::parse::strip_inline_commands;
+use super::commands::CommandCompleter;
+use super::files::SessionFiles;
+use super::history::{Resend, Turn};
+use super::input::{confirm_yes_no, new_editor, open_tty_reader, read_user_input};
+use super::persona::Persona;
 use super::prompt::create_prompt;
+use super::registry::Registry;
+use super::session::{self, Session};
 use super::stream::stream_completion;
 
+/// State of a running chat, shared by the message loop and the slash commands.
+pub struct Chat<'a> {
+    pub args: &'a core::Cli,
+    /// Backend of the next request; /model, /profile and /persona change it.
+    pub service: core::Service,
+    /// Model chosen by configuration, `/profile` or `/model`, used by personas
+    /// that do not set their own.
+    pub base_model: String,
+    pub session: Session,
+    pub persona: Persona,
+    pub registry: Rc<Registry>,
+    /// Piped stdin, sent with the first message.
+    pub pending_stdin: Option<String>,
+    pub last_response: Option<String>,
+    /// Output of `/run` sent with the next message.
+    pub pending_output: Option<String>,
+    pub files: SessionFiles,
+    /// Message queued by a command to be sent before reading new input.
+    pub resend: Option<Resend>,
+    pub stream_enabled: bool,
+    pub policy: ExecPolicy,
+    pub rl: Editor<CommandCompleter, DefaultHistory>,
+    /// When stdin is piped, user input is read from the TTY instead.
+    pub tty_reader: Option<BufReader<File>>,
+}
+
+impl Chat<'_> {
+    /// Saves the session after a change, unless disabled with `NETERO_SESSIONS`,
+    /// reporting failures without stopping the chat.
+    pub fn save(&self) {
+        if !session::saving_enabled() {
+            return;
+        }
+        if let Err(err) = self.session.save(self.service.redactor()) {
+            eprintln!("\nError saving session: {}", err);
+        }
+    }
+}
+
 /// Starts the interactive chat session and handles all supported commands.
 pub async fn generate_chat(
     service: &core::Service,
     args: &core::Cli,
     stdin: String,
     stdin_is_piped: bool,
+    resume: Option<&str>,
 ) {
-    let mut history: Vec<String> = Vec::new();
-    let mut pending_stdin = if stdin.trim().is_empty() {
-        None
-    } else {
-        Some(stdin)
+    let mut session = match resume {
+        Some(id) => match Session::load(id) {
+            Ok(session) => {
+                println!("\nresumed session {}", session.id);
+                session
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                return;
+            }
+        },
+        None => Session::new(),
+    };
+    let mut service = service.clone();
+    let base_model = service.model.clone();
+    // The --persona flag wins over the persona saved with a resumed session.
+    let persona_name = args.persona.clone().or(session.persona.clone());
+    let persona = match persona_name.as_deref().map(Persona::load) {
+        Some(Ok(persona)) => persona,
+        Some(Err(err)) => {
+            eprintln!("{}", err);
+            return;
+        }
+        None => Persona::builtin(),
     };
-    let mut stream_enabled = false;
-    let mut rl = new_editor();
-    // When stdin is piped, read user input from TTY so the chat stays interactive.
-    let mut tty_reader = match open_tty_reader(stdin_is_piped) {
+    if let Some(model) = &persona.model {
+        service.model = model.clone();
+    }
+    session.persona = persona_name;
+    let registry = Rc::new(Registry::load());
+    let tty_reader = match open_tty_reader(stdin_is_piped) {
         Ok(reader) => reader,
         Err(err) => {
             eprintln!("{}", err);
             return;
         }
     };
+    let mut chat = Chat {
+        args,
+        service,
+        base_model,
+        last_response: session.history.last().map(|turn| turn.response.clone()),
+        session,
+        persona,
+        rl: new_editor(&registry),
+        registry,
+        pending_stdin: if stdin.trim().is_empty() {
+            None
+        } else {
+            Some(stdin)
+        },
+        pending_output: None,
+        files: SessionFiles::default(),
+        resend: None,
+        stream_enabled: false,
+        policy: ExecPolicy::from_env(),
+        tty_reader,
+    };
 
     loop {
-        let user_input = match read_user_input(&mut rl, &mut tty_reader) {
-            Ok(Some(line)) => line,
-            Ok(None) => break,
-            Err(err) => {
-                eprintln!("{}", err);
-                break;
+        // A message queued by a command (/retry, /edit, /t, ...) is sent before reading new input.
+        let queued = chat.resend.take();
+        let user_input = match &queued {
+            Some(queued) => queued.input.clone(),
+            None => {
+                let user_input = match read_user_input(
+                    &mut chat.rl,
+                    &mut chat.tty_reader,
+                    &chat.service.model,
+                ) {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        break;
+                    }
+                };
+                if user_input.is_empty() {
+                    continue;
+                }
+                user_input
             }
         };
 
-        if user_input.is_empty() {
-            continue;
-        }
-
-        if handle_clean(&user_input, &mut history) {
-            continue;
-        }
-
-        if handle_help(&user_input) {
-            continue;
-        }
-
-        if handle_add(&user_input, &mut history, &mut pending_stdin) {
-            continue;
-        }
-
-        if handle_stream(&user_input, &mut stream_enabled) {
-            continue;
-        }
-
-        match handle_trans(&user_input, service, args).await {
-            Ok(true) => continue,
-            Ok(false) => {}
-            Err(err) => {
-                eprintln!("{}", err);
-                break;
+        // A rendered template is never a command, whatever its values hold.
+        let source = queued.as_ref().and_then(|queued| queued.source.clone());
+        let registry = chat.registry.clone();
+        if source.is_none() {
+            match registry.dispatch(&mut chat, &user_input).await {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(err) => {
+                    eprintln!("{}", err);
+                    break;
+                }
             }
         }
 
-        match handle_save(&user_input, service, args, &history).await {
-            Ok(true) => continue,
-            Ok(false) => {}
-            Err(err) => {
-                eprintln!("{}", err);
-                break;
-            }
+        // Build the prompt with history, inline command output, and attachments.
+        // Template messages take them from the body, and their values stay text.
+        let dialog = chat.session.history.render(&utils::get_user());
+        let cleaned_input = match source {
+            Some(_) => user_input.clone(),
+            None => inline::strip_inline_commands(&user_input),
+        };
+        let attach_from = source.as_deref().unwrap_or(&user_input);
+        let (_, mut attachments) =
+            attach::extract_attachments_from_input(&inline::strip_inline_commands(attach_from));
+        attachments.extend(inline::read_file_refs(attach_from));
+        if let Some(summary) = attach::summary(&attachments) {
+            eprintln!("\n{}", summary);
         }
-
-        if handle_eval(&user_input) {
+        // Pinned files are sent as they are on disk now.
+        for line in chat.files.refresh(false) {
+            eprintln!("\n{}", line);
+        }
+        let attached: Vec<String> = chat
+            .files
+            .attachments()
+            .into_iter()
+            .chain(&attachments)
+            .map(attach::Attachment::label)
+            .collect();
+        let images = attach::images(chat.files.attachments().into_iter().chain(&attachments));
+        if let Err(err) = chat.service.check_images(&images) {
+            eprintln!("\n{}", err);
             continue;
         }
-
-        // Build the prompt with history, inline command output, and attachments.
-        let dialog = history.join("\n");
-        let command_output = run_inline_commands(&user_input);
-        let cleaned_input = strip_inline_commands(&user_input);
-        let (cleaned_input, attachments) = attach::extract_attachments_from_input(&cleaned_input);
-        let attachment_block = attach::format_attachments(&attachments);
+        // Resent messages keep the /run output they were sent with.
+        let (mut options, run_output, reused_inline) = match queued {
+            Some(queued) => (queued.options, queued.run_output, queued.inline_output),
+            None => (
+                core::RequestOptions::default(),
+                chat.pending_output.take(),
+                None,
+            ),
+        };
+        options.temperature = options.temperature.or(chat.persona.temperature);
+        let inline_output = reused_inline.unwrap_or_else(|| {
+            inline::run_inline_commands(&user_input, &chat.policy, &mut |cmd| {
+                confirm_yes_no(&mut chat.rl, &mut chat.tty_reader, cmd)
+            })
+        });
+        let command_output = match (run_output.clone(), inline_output.clone()) {
+            (Some(ran), Some(inline)) => Some(format!("{}\n\n{}", ran, inline)),
+            (ran, inline) => ran.or(inline),
+        };
+        let attachment_block =
+            attach::format_attachments(chat.files.attachments().into_iter().chain(&attachments));
         let mut merged_stdin = String::new();
-        if let Some(existing) = pending_stdin.as_deref() {
+        if let Some(existing) = chat.pending_stdin.as_deref() {
             merged_stdin.push_str(existing);
         }
         if let Some(extra) = attachment_block.as_deref() {
@@ -106,26 +228,32 @@ pub async fn generate_chat(
         } else {
             Some(merged_stdin)
         };
+        let sent_at = utils::current_datetime();
         let prompt = create_prompt(
             &utils::get_user(),
-            &utils::current_datetime(),
+            &sent_at,
             &utils::get_user_lang(),
+            &chat.persona.role,
+            chat.session
+                .system
+                .as_deref()
+                .unwrap_or(&chat.persona.instructions),
             &dialog,
             &cleaned_input,
             command_output.as_deref(),
             merged_stdin.as_deref(),
         );
-        if pending_stdin.is_some() {
-            pending_stdin = None;
+        if chat.pending_stdin.is_some() {
+            chat.pending_stdin = None;
         }
 
-        if args.verbose {
+        if chat.args.verbose {
             println!("\x1b[32m{}\x1b[0m", prompt);
         }
 
         // Use streaming mode when enabled by the user.
-        let response = if stream_enabled {
-            match stream_completion(service, &prompt).await {
+        let response = if chat.stream_enabled {
+            match stream_completion(&chat.service, &prompt, &images, &options).await {
                 Ok(text) => text,
                 Err(err) => {
                     eprintln!("AI error: {}", err);
@@ -133,7 +261,7 @@ pub async fn generate_chat(
                 }
             }
         } else {
-            match service.complete(&prompt).await {
+            match chat.service.complete_with(&prompt, &images, &options).await {
                 Ok(text) => {
                     let output = render::render_markdown(&text);
                     println!("\n{}", output);
@@ -147,7 +275,18 @@ pub async fn generate_chat(
         };
 
         // Store the last exchange for future context.
-        history.push(format!("{}: {}", utils::get_user(), cleaned_input));
-        history.push(format!("Assistant: {}\n", response));
+        chat.session.history.push(Turn {
+            model: options.model.unwrap_or_else(|| chat.service.model.clone()),
+            input: user_input,
+            cleaned: cleaned_input,
+            response: response.clone(),
+            run_output,
+            inline_output,
+            time: sent_at,
+            attachments: attached,
+            source,
+        });
+        chat.save();
+        chat.last_response = Some(response);
     }
 }
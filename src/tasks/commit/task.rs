@@ -1,4 +1,6 @@
 use crate::core;
+use crate::tasks::attach;
+use crate::tasks::inline::{self, ExecPolicy};
 
 use super::format::{comment, cover, normalize_commit_message};
 use super::git::staged_changes;
@@ -6,19 +8,32 @@ use super::prompts::{convention, instruction, skeleton};
 
 fn generate(hint: Option<&str>, convention_text: &str) -> String {
     // Build a single prompt with all required sections.
-    let user_hint = hint.unwrap_or("");
+    let raw_hint = hint.unwrap_or("");
+    let user_hint = inline::strip_inline_commands(raw_hint);
+
+    // Inline commands and file references in the hint follow the same policy as chat.
+    let policy = ExecPolicy::from_env();
+    let command_output =
+        inline::run_inline_commands(raw_hint, &policy, &mut inline::confirm_on_tty);
+    let file_refs = inline::read_file_refs(raw_hint);
 
     let context = "repository context";
     let staged_changes = staged_changes();
 
-    let sections = [
+    let mut sections = vec![
         ("INSTRUCTION", instruction().to_string()),
         ("CONVENTION", convention_text.to_string()),
         ("SKELETON", skeleton().to_string()),
         ("PROJECT CONTEXT", context.to_string()),
-        ("USER HINT", user_hint.to_string()),
-        ("REPOSITORY STATUS", staged_changes),
+        ("USER HINT", user_hint),
     ];
+    if let Some(output) = command_output {
+        sections.push(("COMMAND OUTPUT", output));
+    }
+    if let Some(files) = attach::format_attached_files(None, &file_refs) {
+        sections.push(("HINT FILES", files));
+    }
+    sections.push(("REPOSITORY STATUS", staged_changes));
 
     sections
         .iter()
@@ -0,0 +1,289 @@
+/// Fenced code block found in a model response.
+pub struct CodeBlock {
+    /// Info string language tag (e.g. `sh`), if any.
+    pub lang: Option<String>,
+    pub content: String,
+}
+
+/// Selects which code block to extract from a response.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CodeSelector {
+    /// First code block in the response.
+    First,
+    /// Nth code block (1-based).
+    Index(usize),
+    /// First code block with the given language tag.
+    Lang(String),
+}
+
+/// Parses an `--extract` spec of the form `code`, `code:N` or `code:lang`.
+pub fn parse_extract_spec(spec: &str) -> Result<CodeSelector, String> {
+    let mut parts = spec.trim().splitn(2, ':');
+    let kind = parts.next().unwrap_or("");
+    if kind != "code" {
+        return Err(format!(
+            "unsupported extract target '{}' (expected code[:lang|:N])",
+            kind
+        ));
+    }
+    match parts.next().map(str::trim) {
+        None | Some("") => Ok(CodeSelector::First),
+        Some(value) => parse_code_selector(value),
+    }
+}
+
+/// Parses a code block selector: a 1-based index or a language tag.
+pub fn parse_code_selector(value: &str) -> Result<CodeSelector, String> {
+    if value.chars().all(|ch| ch.is_ascii_digit()) {
+        return match value.parse::<usize>() {
+            Ok(0) | Err(_) => Err(format!("invalid code block index: {}", value)),
+            Ok(index) => Ok(CodeSelector::Index(index)),
+        };
+    }
+    Ok(CodeSelector::Lang(value.to_ascii_lowercase()))
+}
+
+/// Extracts all fenced code blocks (``` or ~~~) from markdown text.
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<(char, usize, Option<String>, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match current.as_mut() {
+            None => {
+                let Some((fence, len)) = fence_prefix(trimmed) else {
+                    continue;
+                };
+                let info = trimmed[len..].trim();
+                let lang = info
+                    .split(|ch: char| ch.is_whitespace() || ch == ',' || ch == '{')
+                    .next()
+                    .filter(|tag| !tag.is_empty())
+                    .map(|tag| tag.to_ascii_lowercase());
+                current = Some((fence, len, lang, Vec::new()));
+            }
+            Some((fence, len, _, lines)) => {
+                let closes = fence_prefix(trimmed).is_some_and(|(other, other_len)| {
+                    other == *fence && other_len >= *len && trimmed[other_len..].trim().is_empty()
+                });
+                if closes {
+                    let (_, _, lang, lines) = current.take().unwrap();
+                    blocks.push(CodeBlock {
+                        lang,
+                        content: lines.join("\n"),
+                    });
+                } else {
+                    lines.push(line);
+                }
+            }
+        }
+    }
+
+    // An unterminated fence still counts, responses are sometimes truncated.
+    if let Some((_, _, lang, lines)) = current {
+        blocks.push(CodeBlock {
+            lang,
+            content: lines.join("\n"),
+        });
+    }
+
+    blocks
+}
+
+/// Returns the fence character run (``` or ~~~, at least 3) starting the line.
+fn fence_prefix(line: &str) -> Option<(char, usize)> {
+    let first = line.chars().next()?;
+    if first != '`' && first != '~' {
+        return None;
+    }
+    let len = line.chars().take_while(|ch| *ch == first).count();
+    if len < 3 {
+        return None;
+    }
+    Some((first, len))
+}
+
+/// Picks a code block according to the selector.
+pub fn select_code_block<'a>(
+    blocks: &'a [CodeBlock],
+    selector: &CodeSelector,
+) -> Option<&'a CodeBlock> {
+    match selector {
+        CodeSelector::First => blocks.first(),
+        CodeSelector::Index(index) => blocks.get(index.checked_sub(1)?),
+        CodeSelector::Lang(lang) => blocks.iter().find(|block| {
+            block
+                .lang
+                .as_deref()
+                .is_some_and(|tag| lang_matches(tag, lang))
+        }),
+    }
+}
+
+/// Language tags treated as shell code.
+const SHELL_LANGS: [&str; 6] = ["sh", "bash", "shell", "zsh", "console", "shell-session"];
+
+/// Compares language tags, treating common shell aliases as equivalent.
+fn lang_matches(tag: &str, wanted: &str) -> bool {
+    tag == wanted || (SHELL_LANGS.contains(&tag) && SHELL_LANGS.contains(&wanted))
+}
+
+/// Tags of terminal transcripts, where commands follow a `$` or `#` prompt
+/// and the other lines are their output.
+const SESSION_LANGS: [&str; 2] = ["console", "shell-session"];
+
+/// Returns true for code blocks tagged with a shell language. Untagged blocks
+/// are often JSON, logs or plain text, so they are never offered as commands.
+pub fn is_shell_block(block: &CodeBlock) -> bool {
+    block
+        .lang
+        .as_deref()
+        .is_some_and(|tag| SHELL_LANGS.contains(&tag))
+}
+
+/// Commands of a shell block; for terminal transcripts, the prompted lines
+/// without their prompt, plus the lines continuing them after a `\`.
+fn shell_commands(block: &CodeBlock) -> String {
+    if !block
+        .lang
+        .as_deref()
+        .is_some_and(|tag| SESSION_LANGS.contains(&tag))
+    {
+        return block.content.clone();
+    }
+    let mut commands = Vec::new();
+    let mut continued = false;
+    for line in block.content.lines() {
+        let command = if continued {
+            Some(line)
+        } else {
+            let trimmed = line.trim_start();
+            trimmed
+                .strip_prefix("$ ")
+                .or_else(|| trimmed.strip_prefix("# "))
+        };
+        match command {
+            Some(command) => {
+                continued = command.trim_end().ends_with('\\');
+                commands.push(command);
+            }
+            None => continued = false,
+        }
+    }
+    commands.join("\n")
+}
+
+/// Extracts the Nth (1-based) shell code block from a response.
+pub fn extract_shell_code(response: &str, index: usize) -> Result<String, String> {
+    let blocks: Vec<CodeBlock> = code_blocks(response)
+        .into_iter()
+        .filter(is_shell_block)
+        .collect();
+    if blocks.is_empty() {
+        return Err("no shell code blocks found in response".to_string());
+    }
+    let block = index
+        .checked_sub(1)
+        .and_then(|idx| blocks.get(idx))
+        .ok_or_else(|| {
+            format!(
+                "shell code block {} not found ({} available)",
+                index,
+                blocks.len()
+            )
+        })?;
+    let commands = shell_commands(block);
+    if commands.trim().is_empty() {
+        return Err(format!("shell code block {} has no commands", index));
+    }
+    Ok(commands)
+}
+
+/// Extracts the selected code block content from a response.
+pub fn extract_code(response: &str, selector: &CodeSelector) -> Result<String, String> {
+    let blocks = code_blocks(response);
+    if blocks.is_empty() {
+        return Err("no code blocks found in response".to_string());
+    }
+    match select_code_block(&blocks, selector) {
+        Some(block) => Ok(block.content.clone()),
+        None => Err(match selector {
+            CodeSelector::Index(index) => format!(
+                "code block {} not found ({} available)",
+                index,
+                blocks.len()
+            ),
+            CodeSelector::Lang(lang) => format!("no '{}' code block found", lang),
+            CodeSelector::First => "no code blocks found in response".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESPONSE: &str = "Try this:\n\n```sh\nls -la\n```\n\nThen:\n\n```python\nprint(1)\n```\n";
+
+    #[test]
+    fn parses_extract_specs() {
+        assert_eq!(parse_extract_spec("code").unwrap(), CodeSelector::First);
+        assert_eq!(
+            parse_extract_spec("code:sh").unwrap(),
+            CodeSelector::Lang("sh".to_string())
+        );
+        assert_eq!(
+            parse_extract_spec("code:2").unwrap(),
+            CodeSelector::Index(2)
+        );
+        assert!(parse_extract_spec("code:0").is_err());
+        assert!(parse_extract_spec("text").is_err());
+    }
+
+    #[test]
+    fn selects_blocks_by_position_and_language() {
+        assert_eq!(
+            extract_code(RESPONSE, &CodeSelector::First).unwrap(),
+            "ls -la"
+        );
+        assert_eq!(
+            extract_code(RESPONSE, &CodeSelector::Index(2)).unwrap(),
+            "print(1)"
+        );
+        assert_eq!(
+            extract_code(RESPONSE, &CodeSelector::Lang("bash".to_string())).unwrap(),
+            "ls -la"
+        );
+        assert_eq!(
+            extract_code(RESPONSE, &CodeSelector::Index(3)).unwrap_err(),
+            "code block 3 not found (2 available)"
+        );
+        assert!(extract_code("no code", &CodeSelector::First).is_err());
+    }
+
+    #[test]
+    fn longer_fences_contain_shorter_ones() {
+        let text = "````markdown\n```sh\nls\n```\n````\n~~~\nplain\n~~~";
+        let blocks = code_blocks(text);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].lang.as_deref(), Some("markdown"));
+        assert_eq!(blocks[0].content, "```sh\nls\n```");
+        assert_eq!(blocks[1].lang, None);
+        assert_eq!(blocks[1].content, "plain");
+    }
+
+    #[test]
+    fn unterminated_fence_runs_to_the_end() {
+        let blocks = code_blocks("```rust\nfn main() {}\n");
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].content, "fn main() {}");
+    }
+
+    #[test]
+    fn console_blocks_keep_only_prompted_commands() {
+        let text = "```console\n$ echo a \\\n  b\na b\n# id\nuid=0\n```";
+        assert_eq!(extract_shell_code(text, 1).unwrap(), "echo a \\\n  b\nid");
+        assert!(extract_shell_code("```\nls\n```", 1).is_err());
+    }
+}
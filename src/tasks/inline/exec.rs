@@ -0,0 +1,354 @@
+use std::collections::VecDeque;
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, IsTerminal, Read, Write};
+use std::mem;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::parse::{InlineCommand, extract_inline_commands};
+use super::policy::{Decision, ExecLimits, ExecPolicy};
+use super::sandbox;
+
+/// Interval between child status checks and progress updates.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Time left to drain the pipes once the command is over, even past the timeout.
+const DRAIN_GRACE: Duration = Duration::from_millis(200);
+
+/// Captured result of a finished (or killed) command.
+struct CommandOutput {
+    status: ExitStatus,
+    stdout: String,
+    stderr: String,
+    timed_out: bool,
+}
+
+/// Bounded capture buffer that keeps the head and tail of a stream.
+struct Capture {
+    head: Vec<u8>,
+    tail: VecDeque<u8>,
+    half: usize,
+    total: usize,
+}
+
+impl Capture {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            head: Vec::new(),
+            tail: VecDeque::new(),
+            half: (max_bytes / 2).max(1),
+            total: 0,
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        self.total += chunk.len();
+        let room = self.half.saturating_sub(self.head.len());
+        let (head, rest) = chunk.split_at(room.min(chunk.len()));
+        self.head.extend_from_slice(head);
+        self.tail.extend(rest);
+        while self.tail.len() > self.half {
+            self.tail.pop_front();
+        }
+    }
+
+    /// Renders the captured bytes, applying byte and line truncation markers.
+    fn finish(self, max_lines: usize) -> String {
+        let truncated = self.total > self.head.len() + self.tail.len();
+        let head = String::from_utf8_lossy(&self.head).to_string();
+        let tail: Vec<u8> = self.tail.into_iter().collect();
+        let tail = String::from_utf8_lossy(&tail).to_string();
+        if !truncated {
+            return truncate_lines(&(head + &tail), max_lines);
+        }
+        let head_lines = max_lines.div_ceil(2);
+        let tail_lines = max_lines - head_lines;
+        let head: Vec<&str> = head.lines().take(head_lines).collect();
+        let tail: Vec<&str> = tail.lines().collect();
+        let tail = &tail[tail.len().saturating_sub(tail_lines)..];
+        format!(
+            "{}\n[... truncated, {} bytes total ...]\n{}",
+            head.join("\n"),
+            self.total,
+            tail.join("\n")
+        )
+    }
+}
+
+/// Keeps the first and last lines of the text when it exceeds `max_lines`.
+fn truncate_lines(text: &str, max_lines: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    if lines.len() <= max_lines {
+        return text.to_string();
+    }
+    let head = max_lines.div_ceil(2);
+    let tail = max_lines - head;
+    format!(
+        "{}\n[... {} lines truncated ...]\n{}",
+        lines[..head].join("\n"),
+        lines.len() - head - tail,
+        lines[lines.len() - tail..].join("\n")
+    )
+}
+
+fn display_or_empty(text: &str) -> &str {
+    if text.is_empty() { "<empty>" } else { text }
+}
+
+/// Reads a child stream on a background thread into a shared bounded capture.
+fn spawn_reader<R: Read + Send + 'static>(
+    mut stream: R,
+    capture: Arc<Mutex<Capture>>,
+    received: Arc<AtomicUsize>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buf = [0u8; 8192];
+        loop {
+            match stream.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    received.fetch_add(n, Ordering::Relaxed);
+                    if let Ok(mut capture) = capture.lock() {
+                        capture.push(&buf[..n]);
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Takes what a reader captured so far; the reader may still be running when a
+/// process that left the group keeps the pipe open.
+fn take_capture(capture: &Mutex<Capture>) -> Capture {
+    capture
+        .lock()
+        .map(|mut capture| mem::replace(&mut *capture, Capture::new(0)))
+        .unwrap_or_else(|_| Capture::new(0))
+}
+
+/// True once the child has exited, without reaping it. Its pid, which is
+/// also the process group id, stays reserved until `wait`.
+fn has_exited(pid: u32) -> Result<bool, String> {
+    // SAFETY: siginfo_t is plain data, and waitid(2) only writes into it.
+    unsafe {
+        let mut info: libc::siginfo_t = mem::zeroed();
+        let flags = libc::WEXITED | libc::WNOHANG | libc::WNOWAIT;
+        if libc::waitid(libc::P_PID, pid as libc::id_t, &mut info, flags) == -1 {
+            return Err(std::io::Error::last_os_error().to_string());
+        }
+        Ok(info.si_pid() != 0)
+    }
+}
+
+/// Kills every process left in the command's process group.
+fn kill_group(pgid: u32) {
+    // SAFETY: kill(2) on our own child's process group has no memory effects.
+    unsafe {
+        libc::kill(-(pgid as i32), libc::SIGKILL);
+    }
+}
+
+/// Runs a command through `bash -lc` with a timeout, bounded capture and progress display.
+fn execute(cmd: &str, limits: &ExecLimits, sandboxed: bool) -> Result<CommandOutput, String> {
+    let mut command = if sandboxed {
+        sandbox::sandboxed_command(cmd, limits.cwd.as_deref())
+            .map_err(|err| sandbox::describe_error(&err))?
+    } else {
+        let mut command = Command::new("bash");
+        command.args(["-lc", cmd]);
+        if let Some(dir) = &limits.cwd {
+            command.current_dir(dir);
+        }
+        command
+    };
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Own process group so a timeout also kills pipelines and background children.
+        .process_group(0);
+
+    let mut child = command.spawn().map_err(|err| {
+        if sandboxed {
+            sandbox::describe_error(&err)
+        } else {
+            err.to_string()
+        }
+    })?;
+    let received = Arc::new(AtomicUsize::new(0));
+    let stdout = Arc::new(Mutex::new(Capture::new(limits.max_bytes)));
+    let stderr = Arc::new(Mutex::new(Capture::new(limits.max_bytes)));
+    let readers = [
+        spawn_reader(
+            child.stdout.take().expect("piped stdout"),
+            stdout.clone(),
+            received.clone(),
+        ),
+        spawn_reader(
+            child.stderr.take().expect("piped stderr"),
+            stderr.clone(),
+            received.clone(),
+        ),
+    ];
+
+    let started = Instant::now();
+    let show_progress = std::io::stderr().is_terminal();
+    let mut timed_out = false;
+    loop {
+        if has_exited(child.id())? {
+            break;
+        }
+        if started.elapsed() >= limits.timeout {
+            timed_out = true;
+            break;
+        }
+        if show_progress && started.elapsed() >= POLL_INTERVAL * 5 {
+            let mut stderr = std::io::stderr();
+            let _ = write!(
+                stderr,
+                "\r\x1b[2K\x1b[2mrunning: {} ({:.1}s, {} bytes)\x1b[0m",
+                cmd.lines().next().unwrap_or(cmd),
+                started.elapsed().as_secs_f32(),
+                received.load(Ordering::Relaxed)
+            );
+            let _ = stderr.flush();
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+    // Background children would otherwise outlive bash and hold the pipes open.
+    // The group is killed before bash is reaped, so its id cannot be reused yet.
+    kill_group(child.id());
+    let status = child.wait().map_err(|err| err.to_string())?;
+    let deadline = (started + limits.timeout).max(Instant::now() + DRAIN_GRACE);
+    while readers.iter().any(|reader| !reader.is_finished()) && Instant::now() < deadline {
+        thread::sleep(POLL_INTERVAL / 10);
+    }
+    if show_progress {
+        eprint!("\r\x1b[2K");
+    }
+
+    Ok(CommandOutput {
+        status,
+        stdout: take_capture(&stdout).finish(limits.max_lines),
+        stderr: take_capture(&stderr).finish(limits.max_lines),
+        timed_out,
+    })
+}
+
+/// Asks for confirmation on the controlling terminal, for tasks without a line editor.
+/// Stdin and stdout may be pipes there, so both prompt and answer go through /dev/tty.
+pub fn confirm_on_tty(command: &str) -> bool {
+    let Ok(mut tty) = OpenOptions::new().read(true).write(true).open("/dev/tty") else {
+        eprintln!(
+            "\nblocked: {} (no terminal to confirm, see NETERO_EXEC_POLICY)",
+            command
+        );
+        return false;
+    };
+    if write!(tty, "\n\x1b[33m{}\x1b[0m\n\nrun? [y/N] ", command)
+        .and_then(|_| tty.flush())
+        .is_err()
+    {
+        return false;
+    }
+    let mut answer = String::new();
+    if BufReader::new(tty).read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+/// Executes inline shell commands and returns a formatted output section, if any.
+/// `confirm` is called for commands the policy wants the user to approve.
+pub fn run_inline_commands(
+    user_input: &str,
+    policy: &ExecPolicy,
+    confirm: &mut dyn FnMut(&str) -> bool,
+) -> Option<String> {
+    let commands = extract_inline_commands(user_input);
+    run_commands(&commands, policy, confirm)
+}
+
+/// Executes shell commands allowed by the policy and returns a formatted output section, if any.
+/// Commands run sandboxed when written as `#!ro(...)` or when the policy sandboxes everything.
+pub fn run_commands(
+    commands: &[InlineCommand],
+    policy: &ExecPolicy,
+    confirm: &mut dyn FnMut(&str) -> bool,
+) -> Option<String> {
+    if commands.is_empty() {
+        return None;
+    }
+
+    let mut entries = Vec::new();
+
+    for inline in commands {
+        let cmd = inline.command.as_str();
+        let sandboxed = inline.sandboxed || policy.sandbox;
+        // Blocked or rejected commands are still reported so the model knows why output is missing.
+        match policy.check(cmd) {
+            Decision::Run => {}
+            Decision::Ask if confirm(cmd) => {}
+            Decision::Ask => {
+                entries.push(format!(
+                    "[section]\n[command]\n{}\n\n[skipped]\nrejected by user\n[end section]",
+                    cmd
+                ));
+                continue;
+            }
+            Decision::Deny(reason) => {
+                eprintln!("\nblocked: {} ({})", cmd, reason);
+                entries.push(format!(
+                    "[section]\n[command]\n{}\n\n[blocked]\n{}\n[end section]",
+                    cmd, reason
+                ));
+                continue;
+            }
+        }
+
+        let output = execute(cmd, &policy.limits, sandboxed);
+
+        match output {
+            Ok(out) => {
+                let stdout = out.stdout.trim_end().to_string();
+                let stderr = out.stderr.trim_end().to_string();
+
+                if out.timed_out {
+                    entries.push(format!(
+                        "$({})\n[timeout]\nkilled after {}s\n[stderr]\n{}\n[stdout]\n{}",
+                        cmd,
+                        policy.limits.timeout.as_secs(),
+                        display_or_empty(&stderr),
+                        display_or_empty(&stdout)
+                    ));
+                } else if out.status.success() {
+                    entries.push(format!(
+                        "[section]\n[command]\n{}\n\n[stdout]\n{}\n[end section]",
+                        cmd,
+                        display_or_empty(&stdout)
+                    ));
+                    if !stderr.is_empty() {
+                        entries.push(format!("[stderr]\n{}", stderr));
+                    }
+                } else {
+                    entries.push(format!(
+                        "$({})\n[exit status]\n{}\n[stderr]\n{}\n[stdout]\n{}",
+                        cmd,
+                        out.status,
+                        display_or_empty(&stderr),
+                        display_or_empty(&stdout)
+                    ));
+                }
+            }
+            Err(err) => {
+                entries.push(format!("$({})\n[error]\n{}", cmd, err));
+            }
+        }
+    }
+
+    Some(entries.join("\n\n"))
+}
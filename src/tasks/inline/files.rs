@@ -0,0 +1,24 @@
+use super::parse::{FileRef, extract_file_refs};
+use crate::tasks::attach::{self, Attachment};
+
+/// Reads `#!{file:...}` references into attachments labelled with their line range.
+/// Unreadable files are reported on stderr and skipped.
+pub fn read_file_refs(input: &str) -> Vec<Attachment> {
+    extract_file_refs(input)
+        .into_iter()
+        .filter_map(|file| match read_file_ref(&file) {
+            Ok(attachment) => Some(attachment),
+            Err(err) => {
+                eprintln!("\nError reading {}: {}", file.path, err);
+                None
+            }
+        })
+        .collect()
+}
+
+fn read_file_ref(file: &FileRef) -> Result<Attachment, String> {
+    match file.range {
+        Some((start, end)) => attach::read_lines(&file.path, start, end),
+        None => attach::read_file(&file.path),
+    }
+}
@@ -0,0 +1,11 @@
+//! Inline `#!(...)` commands and `#!{file:...}` references shared by all tasks.
+mod exec;
+mod files;
+mod parse;
+pub mod policy;
+mod sandbox;
+
+pub use exec::{confirm_on_tty, run_commands, run_inline_commands};
+pub use files::read_file_refs;
+pub use parse::{InlineCommand, strip_inline_commands};
+pub use policy::ExecPolicy;
@@ -0,0 +1,252 @@
+use crate::tasks::attach::parse_line_range;
+
+/// Inline command parsed from `#!(...)` or the sandboxed `#!ro(...)` form.
+pub struct InlineCommand {
+    pub command: String,
+    /// True when written as `#!ro(...)`.
+    pub sandboxed: bool,
+}
+
+/// Explicit file reference parsed from `#!{file:path}` or `#!{file:path:10-40}`.
+pub struct FileRef {
+    pub path: String,
+    /// Inclusive 1-based line range.
+    pub range: Option<(usize, usize)>,
+}
+
+/// Piece of user input split around inline directives.
+pub enum Segment<'a> {
+    Text(&'a str),
+    Command(InlineCommand),
+    File(FileRef),
+}
+
+/// Splits input into plain text and inline directives in a single pass.
+/// Unterminated directives are kept as text. A command with an unbalanced
+/// quote, like `#!(echo it's)`, ends at the `)` balancing its parentheses with
+/// quotes ignored, so it still runs and the shell reports the quoting error.
+pub fn parse_inline(input: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut text_start = 0;
+    let mut i = 0;
+
+    // Offsets always come from char_indices or ASCII delimiters, so every slice
+    // below falls on a char boundary and multibyte text is copied untouched.
+    while let Some(ch) = input[i..].chars().next() {
+        let Some(opener) = directive_opener(&input[i..]) else {
+            i += ch.len_utf8();
+            continue;
+        };
+        let body_start = i + opener.len;
+        let Some(body_end) = find_closing(input, body_start, &opener, opener.quoted)
+            .or_else(|| find_closing(input, body_start, &opener, false))
+        else {
+            break;
+        };
+        let body = &input[body_start..body_end];
+        let segment = match opener.close {
+            ')' => Some(Segment::Command(InlineCommand {
+                command: body.trim().to_string(),
+                sandboxed: opener.sandboxed,
+            })),
+            _ => parse_file_ref(body).map(Segment::File),
+        };
+        let next = body_end + opener.close.len_utf8();
+        // Unknown `#!{...}` directives stay in the text.
+        if let Some(segment) = segment {
+            if text_start < i {
+                segments.push(Segment::Text(&input[text_start..i]));
+            }
+            if !matches!(&segment, Segment::Command(cmd) if cmd.command.is_empty()) {
+                segments.push(segment);
+            }
+            text_start = next;
+        }
+        i = next;
+    }
+
+    if text_start < input.len() {
+        segments.push(Segment::Text(&input[text_start..]));
+    }
+    segments
+}
+
+/// Directive opener found in the input.
+struct Opener {
+    len: usize,
+    open: char,
+    close: char,
+    sandboxed: bool,
+    /// Shell quoting applies inside command bodies.
+    quoted: bool,
+}
+
+/// Returns the opener when a directive starts the slice.
+fn directive_opener(rest: &str) -> Option<Opener> {
+    let (len, open, close, sandboxed) = if rest.starts_with("#!(") {
+        (3, '(', ')', false)
+    } else if rest.starts_with("#!ro(") {
+        (5, '(', ')', true)
+    } else if rest.starts_with("#!{") {
+        (3, '{', '}', false)
+    } else {
+        return None;
+    };
+    Some(Opener {
+        len,
+        open,
+        close,
+        sandboxed,
+        quoted: open == '(',
+    })
+}
+
+/// Finds the delimiter closing a directive body, honoring nesting and, with
+/// `quoted`, shell quotes and backslash escapes (e.g. `#!(echo ")")`).
+fn find_closing(input: &str, start: usize, opener: &Opener, quoted: bool) -> Option<usize> {
+    let mut depth = 1;
+    let mut quote: Option<char> = None;
+    let mut escape = false;
+
+    for (offset, ch) in input[start..].char_indices() {
+        if quoted {
+            if escape {
+                escape = false;
+                continue;
+            }
+            if ch == '\\' && quote != Some('\'') {
+                escape = true;
+                continue;
+            }
+            if let Some(q) = quote {
+                if ch == q {
+                    quote = None;
+                }
+                continue;
+            }
+            if ch == '"' || ch == '\'' {
+                quote = Some(ch);
+                continue;
+            }
+        }
+        if ch == opener.open {
+            depth += 1;
+        } else if ch == opener.close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(start + offset);
+            }
+        }
+    }
+    None
+}
+
+/// Parses `file:path[:N[-M]]`; anything else is not a known directive.
+fn parse_file_ref(body: &str) -> Option<FileRef> {
+    let spec = body.trim().strip_prefix("file:")?.trim();
+    if spec.is_empty() {
+        return None;
+    }
+    if let Some((path, range)) = spec.rsplit_once(':')
+        && let Some(range) = parse_line_range(range)
+    {
+        return Some(FileRef {
+            path: path.to_string(),
+            range: Some(range),
+        });
+    }
+    Some(FileRef {
+        path: spec.to_string(),
+        range: None,
+    })
+}
+
+/// Extracts inline command substitutions of the form `#!(...)` from a user input string.
+pub fn extract_inline_commands(input: &str) -> Vec<InlineCommand> {
+    parse_inline(input)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Command(cmd) => Some(cmd),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Extracts `#!{file:...}` references from a user input string.
+pub fn extract_file_refs(input: &str) -> Vec<FileRef> {
+    parse_inline(input)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::File(file) => Some(file),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Removes inline directives from the input and trims the result.
+pub fn strip_inline_commands(input: &str) -> String {
+    parse_inline(input)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Text(text) => Some(text),
+            _ => None,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn commands(input: &str) -> Vec<String> {
+        extract_inline_commands(input)
+            .into_iter()
+            .map(|cmd| cmd.command)
+            .collect()
+    }
+
+    #[test]
+    fn keeps_multibyte_text_around_directives() {
+        let input = "¿qué es esto? #!(echo ñandú) → 日本語 #!{file:./a.rs:1-2} ✓";
+        assert_eq!(commands(input), ["echo ñandú"]);
+        assert_eq!(strip_inline_commands(input), "¿qué es esto?  → 日本語  ✓");
+        let files = extract_file_refs(input);
+        assert_eq!(files[0].path, "./a.rs");
+        assert_eq!(files[0].range, Some((1, 2)));
+    }
+
+    #[test]
+    fn skips_nested_and_quoted_parentheses() {
+        assert_eq!(commands("#!(echo $(date) (x)) tail"), ["echo $(date) (x)"]);
+        assert_eq!(
+            commands(r#"#!(echo ")") and #!(echo ')(')"#),
+            [r#"echo ")""#, "echo ')('"]
+        );
+        assert_eq!(commands("#!ro(ls) ok"), ["ls"]);
+    }
+
+    #[test]
+    fn honors_escaped_quotes_and_parentheses() {
+        assert_eq!(commands(r#"#!(echo "a \") b") x"#), [r#"echo "a \") b""#]);
+        assert_eq!(commands(r"#!(echo \)) x"), [r"echo \)"]);
+        assert_eq!(commands(r"#!(echo 'a\') x"), [r"echo 'a\'"]);
+    }
+
+    #[test]
+    fn unbalanced_quote_ends_at_balancing_parenthesis() {
+        let input = "#!(echo it's) and #!(ls)";
+        assert_eq!(commands(input), ["echo it's", "ls"]);
+        assert_eq!(strip_inline_commands(input), "and");
+    }
+
+    #[test]
+    fn unterminated_directive_stays_text() {
+        assert!(commands("run #!(echo oops").is_empty());
+        assert_eq!(
+            strip_inline_commands("run #!(echo oops"),
+            "run #!(echo oops"
+        );
+    }
+}
@@ -0,0 +1,377 @@
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::core;
+
+const DEFAULT_TIMEOUT_SECS: usize = 30;
+const DEFAULT_MAX_BYTES: usize = 32 * 1024;
+const DEFAULT_MAX_LINES: usize = 400;
+
+/// Commands permitted in read-only mode.
+const READ_ONLY_COMMANDS: [&str; 21] = [
+    "ls", "cat", "rg", "grep", "head", "tail", "wc", "pwd", "stat", "file", "tree", "du", "df",
+    "date", "whoami", "uname", "which", "echo", "printf", "realpath", "basename",
+];
+
+/// Options of read-only commands that write files or run other programs.
+const UNSAFE_FLAGS: [(&str, &[&str]); 4] = [
+    ("rg", &["--pre", "--pre-glob"]),
+    ("tree", &["-o", "-R"]),
+    ("file", &["-C", "--compile"]),
+    ("date", &["-s", "--set"]),
+];
+
+/// Git options that write files or run other programs. Those before the
+/// subcommand are global options.
+const UNSAFE_GIT_GLOBAL_FLAGS: [&str; 3] = ["-c", "--config-env", "--exec-path"];
+const UNSAFE_GIT_FLAGS: [&str; 5] = [
+    "--output",
+    "--ext-diff",
+    "--textconv",
+    "-O",
+    "--open-files-in-pager",
+];
+
+/// Git subcommands permitted in read-only mode.
+const READ_ONLY_GIT: [&str; 9] = [
+    "status",
+    "log",
+    "diff",
+    "show",
+    "blame",
+    "ls-files",
+    "rev-parse",
+    "shortlog",
+    "describe",
+];
+
+/// How inline commands are handled when no allow/deny pattern matches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecMode {
+    /// Run every command without asking.
+    Allow,
+    /// Show each command and wait for confirmation.
+    Ask,
+    /// Only run known safe commands; block everything else.
+    ReadOnly,
+}
+
+/// Result of checking a command against the policy.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decision {
+    Run,
+    Ask,
+    Deny(String),
+}
+
+/// Resource limits applied to every executed command.
+pub struct ExecLimits {
+    /// Wall-clock limit; the command's process group is killed when exceeded.
+    pub timeout: Duration,
+    /// Maximum captured bytes per stream, split between head and tail.
+    pub max_bytes: usize,
+    /// Maximum captured lines per stream, split between head and tail.
+    pub max_lines: usize,
+    /// Working directory for commands, defaults to the current directory.
+    pub cwd: Option<PathBuf>,
+}
+
+impl ExecLimits {
+    /// Loads limits from NETERO_EXEC_TIMEOUT, NETERO_EXEC_MAX_BYTES, NETERO_EXEC_MAX_LINES and NETERO_EXEC_CWD.
+    pub fn from_env() -> Self {
+        Self {
+            timeout: Duration::from_secs(
+                core::env_number("NETERO_EXEC_TIMEOUT").unwrap_or(DEFAULT_TIMEOUT_SECS) as u64,
+            ),
+            max_bytes: core::env_number("NETERO_EXEC_MAX_BYTES").unwrap_or(DEFAULT_MAX_BYTES),
+            max_lines: core::env_number("NETERO_EXEC_MAX_LINES").unwrap_or(DEFAULT_MAX_LINES),
+            cwd: env::var("NETERO_EXEC_CWD")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(PathBuf::from),
+        }
+    }
+}
+
+/// Execution policy for `#!(...)` commands, resolved from environment variables.
+pub struct ExecPolicy {
+    pub mode: ExecMode,
+    /// Glob patterns that run without confirmation.
+    pub allow: Vec<String>,
+    /// Glob patterns that are always blocked.
+    pub deny: Vec<String>,
+    /// Run every command in the sandbox, not only `#!ro(...)` ones.
+    pub sandbox: bool,
+    pub limits: ExecLimits,
+}
+
+impl ExecPolicy {
+    /// Loads the policy from NETERO_EXEC_POLICY, NETERO_EXEC_ALLOW, NETERO_EXEC_DENY and NETERO_EXEC_SANDBOX.
+    pub fn from_env() -> Self {
+        let mode = match env::var("NETERO_EXEC_POLICY")
+            .ok()
+            .map(|v| v.trim().to_lowercase())
+            .as_deref()
+        {
+            Some("allow") => ExecMode::Allow,
+            Some("read-only") | Some("readonly") | Some("ro") => ExecMode::ReadOnly,
+            _ => ExecMode::Ask,
+        };
+
+        Self {
+            mode,
+            allow: patterns_from_env("NETERO_EXEC_ALLOW"),
+            deny: patterns_from_env("NETERO_EXEC_DENY"),
+            sandbox: core::env_flag("NETERO_EXEC_SANDBOX"),
+            limits: ExecLimits::from_env(),
+        }
+    }
+
+    /// Decides whether a command may run. Patterns are matched against every
+    /// command of a list, pipeline or substitution, and deny patterns always win.
+    pub fn check(&self, command: &str) -> Decision {
+        let command = command.trim();
+        let segments = command_segments(command);
+        if !self.deny.is_empty() {
+            let Some(segments) = &segments else {
+                return Decision::Deny(
+                    "cannot parse the command to check deny patterns".to_string(),
+                );
+            };
+            for segment in segments {
+                if let Some(pattern) = self.deny.iter().find(|p| glob_match(p, segment)) {
+                    return Decision::Deny(format!("matches deny pattern '{}'", pattern));
+                }
+            }
+        }
+        // Only allowed when every command on the line is.
+        if let Some(segments) = &segments
+            && !segments.is_empty()
+            && segments
+                .iter()
+                .all(|segment| self.allow.iter().any(|p| glob_match(p, segment)))
+        {
+            return Decision::Run;
+        }
+        match self.mode {
+            ExecMode::Allow => Decision::Run,
+            ExecMode::Ask => Decision::Ask,
+            ExecMode::ReadOnly => match read_only_violation(command) {
+                None => Decision::Run,
+                Some(reason) => Decision::Deny(format!("read-only mode: {}", reason)),
+            },
+        }
+    }
+}
+
+/// Reads a comma-separated pattern list from an environment variable.
+fn patterns_from_env(key: &str) -> Vec<String> {
+    env::var(key)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Matches a command against a glob pattern where `*` spans any text and `?` one char.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|ch| *ch == '*')
+}
+
+/// Splits a command line into the commands it runs: the parts of lists and
+/// pipelines (`;`, `&&`, `||`, `|`, `&`, newlines), subshells and `$(...)` or
+/// backtick substitutions. Quotes are removed from the words. `None` when
+/// quotes or substitutions are unbalanced.
+fn command_segments(command: &str) -> Option<Vec<String>> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    // Quote state of each enclosing `$(...)`, restored when it closes.
+    let mut outer: Vec<Option<char>> = Vec::new();
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars().peekable();
+    let split = |current: &mut String, segments: &mut Vec<String>| {
+        let segment = current.trim();
+        if !segment.is_empty() {
+            segments.push(segment.to_string());
+        }
+        current.clear();
+    };
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => current.push(ch),
+            (_, '\\') => {
+                current.push(ch);
+                current.extend(chars.next());
+            }
+            (_, '$') if chars.peek() == Some(&'(') => {
+                chars.next();
+                outer.push(quote);
+                quote = None;
+                split(&mut current, &mut segments);
+            }
+            (Some('"'), '"') => quote = None,
+            (Some('"'), '`') => split(&mut current, &mut segments),
+            (Some('"'), _) => current.push(ch),
+            (None, '\'' | '"') => quote = Some(ch),
+            (None, ')') if !outer.is_empty() => {
+                quote = outer.pop().flatten();
+                split(&mut current, &mut segments);
+            }
+            (None, ';' | '&' | '|' | '\n' | '(' | ')' | '`') => split(&mut current, &mut segments),
+            _ => current.push(ch),
+        }
+    }
+    if quote.is_some() || !outer.is_empty() {
+        return None;
+    }
+    split(&mut current, &mut segments);
+    Some(segments)
+}
+
+/// Returns why a command is not read-only safe, or `None` when it is.
+fn read_only_violation(command: &str) -> Option<String> {
+    for token in ["$(", "`", ">", "<(", "\n"] {
+        if command.contains(token) {
+            return Some(format!("'{}' is not allowed", token.escape_default()));
+        }
+    }
+
+    // Every segment of a pipeline or command list must be safe on its own.
+    let Some(segments) = command_segments(command) else {
+        return Some("unbalanced quotes".to_string());
+    };
+    for segment in &segments {
+        // Escapes are dropped so `\--pre=sh` is seen as the option it is.
+        let words: Vec<String> = segment
+            .split_whitespace()
+            .map(|word| word.replace('\\', ""))
+            .collect();
+        let program = words.first().map(String::as_str).unwrap_or("");
+        let args = words.get(1..).unwrap_or_default();
+        if program == "git" {
+            let sub_idx = args.iter().position(|w| !w.starts_with('-'));
+            let (global, rest) = args.split_at(sub_idx.unwrap_or(args.len()));
+            let sub = rest.first().map(String::as_str).unwrap_or("");
+            if !READ_ONLY_GIT.contains(&sub) {
+                return Some(format!("'git {}' is not a read-only command", sub));
+            }
+            if let Some(flag) = find_flag(global, &UNSAFE_GIT_GLOBAL_FLAGS)
+                .or_else(|| find_flag(&rest[1..], &UNSAFE_GIT_FLAGS))
+            {
+                return Some(format!("'git {}' is not allowed", flag));
+            }
+        } else if !READ_ONLY_COMMANDS.contains(&program) {
+            return Some(format!("'{}' is not a read-only command", program));
+        } else if let Some((_, flags)) = UNSAFE_FLAGS.iter().find(|(name, _)| *name == program)
+            && let Some(flag) = find_flag(args, flags)
+        {
+            return Some(format!("'{} {}' is not allowed", program, flag));
+        }
+    }
+    None
+}
+
+/// First of `flags` given in `args`, as `--long`, `--long=value`, `-s` or
+/// within a group of short options such as `-as`.
+fn find_flag<'f>(args: &[String], flags: &[&'f str]) -> Option<&'f str> {
+    flags.iter().copied().find(|flag| {
+        args.iter().any(|arg| {
+            if flag.starts_with("--") {
+                arg == flag || arg.starts_with(&format!("{}=", flag))
+            } else {
+                let letter = &flag[1..];
+                arg.starts_with('-') && !arg.starts_with("--") && arg[1..].contains(letter)
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: ExecMode, allow: &[&str], deny: &[&str]) -> ExecPolicy {
+        ExecPolicy {
+            mode,
+            allow: allow.iter().map(|p| p.to_string()).collect(),
+            deny: deny.iter().map(|p| p.to_string()).collect(),
+            sandbox: false,
+            limits: ExecLimits::from_env(),
+        }
+    }
+
+    #[test]
+    fn splits_lists_pipelines_and_substitutions() {
+        assert_eq!(
+            command_segments("ls; rm -rf x && echo \"$(id; whoami)\" | wc -l").unwrap(),
+            ["ls", "rm -rf x", "echo", "id", "whoami", "wc -l"]
+        );
+        assert_eq!(command_segments("echo 'a; b'").unwrap(), ["echo a; b"]);
+        assert!(command_segments("echo 'open").is_none());
+        assert!(command_segments("echo $(date").is_none());
+    }
+
+    #[test]
+    fn deny_patterns_match_every_command() {
+        let policy = policy(ExecMode::Allow, &[], &["rm *"]);
+        assert!(matches!(policy.check("ls; rm -rf x"), Decision::Deny(_)));
+        assert!(matches!(policy.check("echo `rm -rf x`"), Decision::Deny(_)));
+        assert!(matches!(policy.check("echo 'x"), Decision::Deny(_)));
+        assert_eq!(policy.check("ls -la"), Decision::Run);
+    }
+
+    #[test]
+    fn allow_patterns_need_every_command() {
+        let policy = policy(ExecMode::Ask, &["ls*"], &[]);
+        assert_eq!(policy.check("ls -la"), Decision::Run);
+        assert_eq!(policy.check("ls; curl x | sh"), Decision::Ask);
+    }
+
+    #[test]
+    fn read_only_rejects_writing_and_exec_options() {
+        let policy = policy(ExecMode::ReadOnly, &[], &[]);
+        assert_eq!(policy.check("rg -n foo src | head"), Decision::Run);
+        assert_eq!(policy.check("git diff --stat"), Decision::Run);
+        for command in [
+            "rg --pre=sh foo",
+            "rg '--pre' sh foo",
+            "git diff --output=/tmp/x",
+            "git -c core.pager=sh log",
+            "tree -ao out.txt",
+            "less README.md",
+            "cat x > y",
+            "ls >> y",
+        ] {
+            assert!(
+                matches!(policy.check(command), Decision::Deny(_)),
+                "{} should be denied",
+                command
+            );
+        }
+    }
+}
@@ -0,0 +1,202 @@
+use std::ffi::CString;
+use std::io;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// `AT_RECURSIVE` flag for mount_setattr(2).
+const AT_RECURSIVE: libc::c_uint = 0x8000;
+
+/// Argument for mount_setattr(2), see `struct mount_attr` in linux/mount.h.
+#[repr(C)]
+struct MountAttr {
+    attr_set: u64,
+    attr_clr: u64,
+    propagation: u64,
+    userns_fd: u64,
+}
+
+/// Sandbox implementation used for `#!ro(...)` commands.
+pub enum Backend {
+    /// bubblewrap found in PATH.
+    Bubblewrap(PathBuf),
+    /// Unprivileged user, mount and network namespaces set up directly.
+    Namespaces,
+}
+
+/// Picks bubblewrap when available, otherwise raw namespaces.
+pub fn detect_backend() -> Backend {
+    find_in_path("bwrap")
+        .map(Backend::Bubblewrap)
+        .unwrap_or(Backend::Namespaces)
+}
+
+fn find_in_path(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Builds a `bash -lc` command that runs with a read-only filesystem, a private
+/// tmpfs on /tmp and no network access.
+pub fn sandboxed_command(cmd: &str, cwd: Option<&Path>) -> io::Result<Command> {
+    match detect_backend() {
+        Backend::Bubblewrap(bwrap) => {
+            let mut command = Command::new(bwrap);
+            command.args([
+                "--ro-bind",
+                "/",
+                "/",
+                "--dev",
+                "/dev",
+                "--proc",
+                "/proc",
+                "--tmpfs",
+                "/tmp",
+                "--unshare-all",
+                "--die-with-parent",
+            ]);
+            if let Some(dir) = cwd {
+                command.arg("--chdir").arg(dir);
+            }
+            command.args(["bash", "-lc", cmd]);
+            Ok(command)
+        }
+        Backend::Namespaces => {
+            let mut command = Command::new("bash");
+            command.args(["-lc", cmd]);
+            if let Some(dir) = cwd {
+                command.current_dir(dir);
+            }
+            let setup = NamespaceSetup::new()?;
+            // SAFETY: the closure only performs raw syscalls on data prepared before fork.
+            unsafe {
+                command.pre_exec(move || setup.apply());
+            }
+            Ok(command)
+        }
+    }
+}
+
+/// Translates a spawn error into a user-facing message for sandboxed commands.
+pub fn describe_error(err: &io::Error) -> String {
+    match err.raw_os_error() {
+        Some(libc::EPERM) | Some(libc::EACCES) | Some(libc::EINVAL) | Some(libc::ENOSPC) => {
+            format!(
+                "sandbox unavailable: the kernel does not allow unprivileged user namespaces ({}). \
+Enable user.max_user_namespaces / kernel.unprivileged_userns_clone, install bubblewrap, \
+or use #!(...) instead",
+                err
+            )
+        }
+        Some(libc::ENOSYS) => format!(
+            "sandbox unavailable: kernel lacks mount_setattr(2), Linux 5.12+ is required ({})",
+            err
+        ),
+        _ => format!("sandbox error: {}", err),
+    }
+}
+
+/// Namespace setup data, allocated before fork so the child only makes syscalls.
+struct NamespaceSetup {
+    uid_map: Vec<u8>,
+    gid_map: Vec<u8>,
+    setgroups_path: CString,
+    uid_map_path: CString,
+    gid_map_path: CString,
+    root: CString,
+    tmp: CString,
+    tmpfs: CString,
+    tmpfs_options: CString,
+}
+
+impl NamespaceSetup {
+    fn new() -> io::Result<Self> {
+        // SAFETY: getuid/getgid never fail.
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let cstr = |s: &str| CString::new(s).map_err(io::Error::other);
+        Ok(Self {
+            uid_map: format!("{uid} {uid} 1").into_bytes(),
+            gid_map: format!("{gid} {gid} 1").into_bytes(),
+            setgroups_path: cstr("/proc/self/setgroups")?,
+            uid_map_path: cstr("/proc/self/uid_map")?,
+            gid_map_path: cstr("/proc/self/gid_map")?,
+            root: cstr("/")?,
+            tmp: cstr("/tmp")?,
+            tmpfs: cstr("tmpfs")?,
+            tmpfs_options: cstr("size=64m,mode=1777")?,
+        })
+    }
+
+    /// Runs in the forked child: new namespaces, read-only root, tmpfs /tmp.
+    fn apply(&self) -> io::Result<()> {
+        // SAFETY: all pointers reference CStrings/buffers owned by `self`.
+        unsafe {
+            check(libc::unshare(
+                libc::CLONE_NEWUSER | libc::CLONE_NEWNS | libc::CLONE_NEWNET,
+            ))?;
+            write_file(&self.setgroups_path, b"deny")?;
+            write_file(&self.uid_map_path, &self.uid_map)?;
+            write_file(&self.gid_map_path, &self.gid_map)?;
+
+            // Keep our mount changes from propagating back to the host.
+            check(libc::mount(
+                std::ptr::null(),
+                self.root.as_ptr(),
+                std::ptr::null(),
+                libc::MS_REC | libc::MS_PRIVATE,
+                std::ptr::null(),
+            ))?;
+
+            let attr = MountAttr {
+                attr_set: libc::MOUNT_ATTR_RDONLY,
+                attr_clr: 0,
+                propagation: 0,
+                userns_fd: 0,
+            };
+            check(libc::syscall(
+                libc::SYS_mount_setattr,
+                libc::AT_FDCWD,
+                self.root.as_ptr(),
+                AT_RECURSIVE,
+                &attr as *const MountAttr,
+                std::mem::size_of::<MountAttr>(),
+            ) as libc::c_int)?;
+
+            check(libc::mount(
+                self.tmpfs.as_ptr(),
+                self.tmp.as_ptr(),
+                self.tmpfs.as_ptr(),
+                libc::MS_NOSUID | libc::MS_NODEV,
+                self.tmpfs_options.as_ptr() as *const libc::c_void,
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+fn check(ret: libc::c_int) -> io::Result<()> {
+    if ret == -1 {
+        Err(io::Error::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes a buffer to a file with raw syscalls (safe to call after fork).
+unsafe fn write_file(path: &CString, data: &[u8]) -> io::Result<()> {
+    // SAFETY: path is NUL-terminated and data outlives the call.
+    unsafe {
+        let fd = libc::open(path.as_ptr(), libc::O_WRONLY | libc::O_CLOEXEC);
+        if fd == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        let written = libc::write(fd, data.as_ptr() as *const libc::c_void, data.len());
+        libc::close(fd);
+        if written == -1 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
@@ -2,3 +2,4 @@
 pub mod task;
 
 pub use task::generate_message as connect;
+pub use task::{parse_selector, send_message};
@@ -1,5 +1,7 @@
 use crate::core;
 use crate::tasks::attach;
+use crate::tasks::extract;
+use crate::tasks::inline::{self, ExecPolicy};
 use crate::tasks::render;
 use crate::utils;
 
@@ -8,11 +10,53 @@ pub async fn generate_message(
     args: &core::Cli,
     request: &str,
     stdin: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let selector = parse_selector(args)?;
+    let policy = ExecPolicy::from_env();
+    let command_output = inline::run_inline_commands(request, &policy, &mut inline::confirm_on_tty);
+    let stripped_request = inline::strip_inline_commands(request);
+    send_message(
+        service,
+        args,
+        selector,
+        &stripped_request,
+        request,
+        command_output,
+        stdin,
+    )
+    .await
+}
+
+/// Parses `--extract` before anything runs, so a bad spec fails early.
+pub fn parse_selector(args: &core::Cli) -> Result<Option<extract::CodeSelector>, String> {
+    args.extract
+        .as_deref()
+        .map(extract::parse_extract_spec)
+        .transpose()
+}
+
+/// Sends `request` whose inline commands already ran, attaching the paths
+/// and `#!{file:...}` references found in `source`.
+pub async fn send_message(
+    service: &core::Service,
+    args: &core::Cli,
+    selector: Option<extract::CodeSelector>,
+    request: &str,
+    source: &str,
+    command_output: Option<String>,
+    stdin: String,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let user_lang = utils::get_user_lang();
     let user_lang = utils::normalize_lang_tag(&user_lang);
     let user = utils::get_user();
-    let (cleaned_request, attachments) = attach::extract_attachments_from_input(request);
+    let (_, mut attachments) =
+        attach::extract_attachments_from_input(&inline::strip_inline_commands(source));
+    attachments.extend(inline::read_file_refs(source));
+    if let Some(summary) = attach::summary(&attachments) {
+        eprintln!("{}", summary);
+    }
+    let images = attach::images(&attachments);
+    service.check_images(&images)?;
     let stdin_content = stdin;
     let attached_files = attach::format_attached_files(
         if stdin_content.trim().is_empty() {
@@ -26,14 +70,26 @@ pub async fn generate_message(
     let mut prompt = format!(
         "USER LANG: {} !important\n:: USER HINT ::\n{}\n:: END USER HINT ::",
         user_lang,
-        cleaned_request.trim()
+        request.trim()
     );
+    if let Some(output) = command_output {
+        prompt.push_str("\n\n:: COMMAND OUTPUT ::\n");
+        prompt.push_str(&output);
+        prompt.push_str("\n:: END COMMAND OUTPUT ::");
+    }
     if let Some(block) = attached_files {
         prompt.push_str("\n\n");
         prompt.push_str(&block);
     }
 
-    let response = service.complete(&prompt).await?;
+    let response = service.complete_with_images(&prompt, &images).await?;
+
+    if let Some(selector) = selector {
+        // Raw output so the block can be piped straight into other tools.
+        let code = extract::extract_code(&response, &selector)?;
+        println!("{}", code);
+        return Ok(());
+    }
 
     if args.verbose {
         println!("\x1b[1m{}:\x1b[0m\n\n{}\n", user.to_uppercase(), prompt);
@@ -0,0 +1,363 @@
+//! Reusable prompt templates with variables, for one-shot and chat use.
+pub mod task;
+
+use regex::{Captures, Regex};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+use crate::tasks::attach;
+use crate::tasks::inline::{self, ExecPolicy};
+use crate::utils;
+
+pub use task::{list_templates, run_template};
+
+/// Variable declared in a template's front-matter, with an optional default.
+pub struct Var {
+    pub name: String,
+    pub default: Option<String>,
+}
+
+/// Template filled in for sending. Values are plain text: only the body's
+/// inline commands run, and only the body is searched for attachments.
+pub struct Rendered {
+    /// Body without its inline commands, with the values substituted.
+    pub text: String,
+    /// Body before substitution, where paths and file references are found.
+    pub source: String,
+    /// Output of the body's inline commands.
+    pub command_output: Option<String>,
+}
+
+/// Prompt file from `templates/<name>.md` in the config directory.
+pub struct Template {
+    pub name: String,
+    pub description: String,
+    pub vars: Vec<Var>,
+    /// Model used instead of the configured one.
+    pub model: Option<String>,
+    pub body: String,
+}
+
+impl Template {
+    /// Loads a template. The front-matter may set `description`, `model` and
+    /// `vars` (comma-separated names, each optionally `name=default`).
+    pub fn load(name: &str) -> Result<Self, String> {
+        let dir = templates_dir().ok_or_else(|| "no config directory".to_string())?;
+        let text = fs::read_to_string(dir.join(format!("{}.md", name))).map_err(|err| {
+            if err.kind() == ErrorKind::NotFound {
+                format!("template '{}' not found", name)
+            } else {
+                format!("template '{}': {}", name, err)
+            }
+        })?;
+
+        let (fields, body) = utils::split_front_matter(&text);
+        let mut template = Self {
+            name: name.to_string(),
+            description: String::new(),
+            vars: Vec::new(),
+            model: None,
+            body: body.to_string(),
+        };
+        for (key, value) in fields {
+            match key.as_str() {
+                "description" => template.description = value,
+                "model" if !value.is_empty() => template.model = Some(value),
+                "vars" => {
+                    template.vars = value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|var| !var.is_empty())
+                        .map(|var| match var.split_once('=') {
+                            Some((name, default)) => Var {
+                                name: name.trim().to_string(),
+                                default: Some(default.trim().to_string()),
+                            },
+                            None => Var {
+                                name: var.to_string(),
+                                default: None,
+                            },
+                        })
+                        .collect()
+                }
+                _ => {}
+            }
+        }
+        Ok(template)
+    }
+
+    /// Matches `key=value` arguments to variables; other arguments fill the
+    /// declared variables in order.
+    pub fn assign(&self, args: &[String]) -> Result<Vec<(String, String)>, String> {
+        let mut values: Vec<(String, String)> = Vec::new();
+        let mut positional = Vec::new();
+        for arg in args {
+            match arg.split_once('=') {
+                Some((key, value)) if self.vars.iter().any(|var| var.name == key) => {
+                    values.push((key.to_string(), value.to_string()))
+                }
+                _ => positional.push(arg.clone()),
+            }
+        }
+        let free: Vec<&Var> = self
+            .vars
+            .iter()
+            .filter(|var| !values.iter().any(|(key, _)| *key == var.name))
+            .collect();
+        let mut free = free.into_iter();
+        for value in positional {
+            let var = free.next().ok_or_else(|| {
+                format!("template '{}': unexpected argument '{}'", self.name, value)
+            })?;
+            values.push((var.name.clone(), value));
+        }
+        Ok(values)
+    }
+
+    /// Replaces `{{name}}` placeholders, resolving given values and defaults
+    /// with `resolve_value`. `{{stdin}}` is always available. The body's
+    /// inline commands run before substitution, so placeholders inside them
+    /// stay as written.
+    pub fn render(
+        &self,
+        values: &[(String, String)],
+        stdin: &str,
+        policy: &ExecPolicy,
+        confirm: &mut dyn FnMut(&str) -> bool,
+    ) -> Result<Rendered, String> {
+        let mut resolved = vec![("stdin".to_string(), stdin.trim_end().to_string())];
+        let mut missing = Vec::new();
+        for var in &self.vars {
+            let raw = values
+                .iter()
+                .find(|(key, _)| *key == var.name)
+                .map(|(_, value)| value.as_str())
+                .or(var.default.as_deref());
+            match raw {
+                Some(raw) => resolved.push((
+                    var.name.clone(),
+                    resolve_value(raw, stdin, policy, confirm)?,
+                )),
+                None => missing.push(var.name.as_str()),
+            }
+        }
+        if !missing.is_empty() {
+            return Err(format!(
+                "template '{}' needs: {}",
+                self.name,
+                missing.join(", ")
+            ));
+        }
+
+        let command_output = inline::run_inline_commands(&self.body, policy, confirm);
+        let body = inline::strip_inline_commands(&self.body);
+        let text = placeholder().replace_all(&body, |caps: &Captures| {
+            resolved
+                .iter()
+                .find(|(key, _)| *key == caps[1])
+                .map_or(caps[0].to_string(), |(_, value)| value.clone())
+        });
+        Ok(Rendered {
+            text: text.trim().to_string(),
+            source: self.body.clone(),
+            command_output,
+        })
+    }
+
+    /// True when the body reads piped input through `{{stdin}}` or a `-` value.
+    pub fn uses_stdin(&self, values: &[(String, String)]) -> bool {
+        placeholder()
+            .captures_iter(&self.body)
+            .any(|caps| &caps[1] == "stdin")
+            || values.iter().any(|(_, value)| value == "-")
+            || self.vars.iter().any(|var| {
+                var.default.as_deref() == Some("-")
+                    && !values.iter().any(|(key, _)| *key == var.name)
+            })
+    }
+}
+
+/// Resolves a variable value: `-` is piped stdin, `@path` the contents of a
+/// file and a value containing `#!(...)` the output of those commands.
+fn resolve_value(
+    raw: &str,
+    stdin: &str,
+    policy: &ExecPolicy,
+    confirm: &mut dyn FnMut(&str) -> bool,
+) -> Result<String, String> {
+    if raw == "-" {
+        return Ok(stdin.trim_end().to_string());
+    }
+    if let Some(path) = raw.strip_prefix('@') {
+        return attach::read_file(path)
+            .map(|file| file.content)
+            .map_err(|err| format!("{}: {}", path, err));
+    }
+    if raw.contains("#!(") || raw.contains("#!ro(") {
+        return Ok(inline::run_inline_commands(raw, policy, confirm).unwrap_or_default());
+    }
+    Ok(raw.to_string())
+}
+
+/// Every template in the config directory, sorted by name. Files that fail
+/// to load are skipped.
+pub fn all_templates() -> Vec<Template> {
+    let Some(entries) = templates_dir().and_then(|dir| fs::read_dir(dir).ok()) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+        .collect();
+    names.sort();
+    names
+        .iter()
+        .filter_map(|name| Template::load(name).ok())
+        .collect()
+}
+
+/// One line per template with its description and variables.
+pub fn format_list(templates: &[Template]) -> String {
+    if templates.is_empty() {
+        return "no templates found".to_string();
+    }
+    templates
+        .iter()
+        .map(|template| {
+            let mut line = template.name.clone();
+            if !template.description.is_empty() {
+                line.push_str(&format!("  {}", template.description));
+            }
+            if !template.vars.is_empty() {
+                let vars: Vec<&str> = template.vars.iter().map(|var| var.name.as_str()).collect();
+                line.push_str(&format!("  [{}]", vars.join(", ")));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Matches `{{name}}`, capturing the variable name.
+fn placeholder() -> Regex {
+    Regex::new(r"\{\{\s*([A-Za-z0-9_-]+)\s*\}\}").unwrap()
+}
+
+fn templates_dir() -> Option<PathBuf> {
+    utils::config_dir().map(|dir| dir.join("templates"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::inline::policy::{ExecLimits, ExecMode};
+
+    fn template(vars: &str, body: &str) -> Template {
+        let mut template = Template {
+            name: "t".to_string(),
+            description: String::new(),
+            vars: Vec::new(),
+            model: None,
+            body: body.to_string(),
+        };
+        for var in vars.split(',').map(str::trim).filter(|var| !var.is_empty()) {
+            template.vars.push(match var.split_once('=') {
+                Some((name, default)) => Var {
+                    name: name.to_string(),
+                    default: Some(default.to_string()),
+                },
+                None => Var {
+                    name: var.to_string(),
+                    default: None,
+                },
+            });
+        }
+        template
+    }
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn policy() -> ExecPolicy {
+        ExecPolicy {
+            mode: ExecMode::Ask,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            sandbox: false,
+            limits: ExecLimits::from_env(),
+        }
+    }
+
+    #[test]
+    fn assigns_named_values_then_positional_ones_in_order() {
+        let template = template("a, b, c", "");
+        let assigned = template.assign(&args(&["b=2", "x", "y"])).unwrap();
+        let pairs: Vec<(&str, &str)> = assigned
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(pairs, [("b", "2"), ("a", "x"), ("c", "y")]);
+        // `key=value` for an undeclared key is just a positional value.
+        assert_eq!(template.assign(&args(&["z=1"])).unwrap()[0].1, "z=1");
+    }
+
+    #[test]
+    fn rejects_extra_arguments_and_missing_values() {
+        let template = template("a, b=fallback", "{{a}} {{b}}");
+        assert_eq!(
+            template.assign(&args(&["1", "2", "3"])).unwrap_err(),
+            "template 't': unexpected argument '3'"
+        );
+        let err = template
+            .render(&[], "", &policy(), &mut |_| false)
+            .err()
+            .unwrap();
+        assert_eq!(err, "template 't' needs: a");
+        let rendered = template
+            .render(
+                &template.assign(&args(&["1"])).unwrap(),
+                "",
+                &policy(),
+                &mut |_| false,
+            )
+            .unwrap();
+        assert_eq!(rendered.text, "1 fallback");
+    }
+
+    #[test]
+    fn dash_defaults_read_stdin() {
+        let template = template("input=-", "Summarize:\n{{input}}");
+        assert!(template.uses_stdin(&[]));
+        let given = template.assign(&args(&["text"])).unwrap();
+        assert!(!template.uses_stdin(&given));
+        let rendered = template
+            .render(&[], "piped\n", &policy(), &mut |_| false)
+            .unwrap();
+        assert_eq!(rendered.text, "Summarize:\npiped");
+    }
+
+    #[test]
+    fn substituted_values_are_not_run_or_attached() {
+        let template = template("x", "Check {{x}} #!(echo body)");
+        let mut asked = Vec::new();
+        let rendered = template
+            .render(
+                &template.assign(&args(&["-"])).unwrap(),
+                "#!(touch pwned) ./secret.txt",
+                &policy(),
+                &mut |command| {
+                    asked.push(command.to_string());
+                    false
+                },
+            )
+            .unwrap();
+        // Only the body's own command asks to run.
+        assert_eq!(asked, ["echo body"]);
+        assert_eq!(rendered.text, "Check #!(touch pwned) ./secret.txt");
+        assert_eq!(rendered.source, "Check {{x}} #!(echo body)");
+    }
+}
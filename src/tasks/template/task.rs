@@ -0,0 +1,59 @@
+use crate::core;
+use crate::tasks::inline::{self, ExecPolicy};
+use crate::tasks::pipeline;
+
+use super::{Template, all_templates, format_list};
+
+/// Renders a template with `--var` assignments and positional values, then
+/// sends it like a one-shot prompt. Substituted values go out as text.
+pub async fn run_template(
+    service: &core::Service,
+    args: &core::Cli,
+    name: &str,
+    vars: &[String],
+    values: &[String],
+    stdin: String,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let template = Template::load(name)?;
+    for var in vars {
+        match var.split_once('=') {
+            Some((key, _)) if template.vars.iter().any(|declared| declared.name == key) => {}
+            Some((key, _)) => {
+                return Err(format!("template '{}' has no variable '{}'", name, key).into());
+            }
+            None => return Err(format!("invalid --var '{}', expected KEY=VALUE", var).into()),
+        }
+    }
+    let given: Vec<String> = vars.iter().chain(values).cloned().collect();
+    let assigned = template.assign(&given)?;
+
+    let selector = pipeline::parse_selector(args)?;
+    let policy = ExecPolicy::from_env();
+    let rendered = template.render(&assigned, &stdin, &policy, &mut inline::confirm_on_tty)?;
+    // Piped input already placed in the prompt is not attached a second time.
+    let stdin = if template.uses_stdin(&assigned) {
+        String::new()
+    } else {
+        stdin
+    };
+
+    let mut service = service.clone();
+    if let Some(model) = &template.model {
+        service.model = model.clone();
+    }
+    pipeline::send_message(
+        &service,
+        args,
+        selector,
+        &rendered.text,
+        &rendered.source,
+        rendered.command_output,
+        stdin,
+    )
+    .await
+}
+
+/// Prints the available templates.
+pub fn list_templates() {
+    println!("{}", format_list(&all_templates()));
+}
@@ -0,0 +1,50 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+
+/// Largest encoded payload sent in one OSC 52 sequence; many terminals drop bigger ones.
+const MAX_OSC52_BYTES: usize = 100_000;
+
+/// Copies text to the system clipboard using the OSC 52 terminal escape sequence.
+/// Works over SSH and inside tmux/screen, as long as the outer terminal allows it.
+pub fn copy_to_clipboard(text: &str) -> Result<usize, String> {
+    let encoded = STANDARD.encode(text.as_bytes());
+    if encoded.len() > MAX_OSC52_BYTES {
+        return Err(format!(
+            "text too large for clipboard ({} bytes, max {} encoded)",
+            text.len(),
+            MAX_OSC52_BYTES
+        ));
+    }
+
+    let sequence = wrap_for_multiplexer(&format!("\x1b]52;c;{}\x07", encoded));
+
+    // Prefer the controlling terminal so piped stdout does not swallow the sequence.
+    let written = match OpenOptions::new().write(true).open("/dev/tty") {
+        Ok(mut tty) => tty.write_all(sequence.as_bytes()).and_then(|_| tty.flush()),
+        Err(_) => {
+            let mut stdout = std::io::stdout();
+            stdout
+                .write_all(sequence.as_bytes())
+                .and_then(|_| stdout.flush())
+        }
+    };
+
+    written
+        .map(|_| text.len())
+        .map_err(|err| format!("clipboard error: {}", err))
+}
+
+/// Wraps an escape sequence in the passthrough envelope used by tmux or screen.
+fn wrap_for_multiplexer(sequence: &str) -> String {
+    if env::var("TMUX").is_ok_and(|v| !v.is_empty()) {
+        // tmux requires every ESC inside the payload to be doubled.
+        return format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"));
+    }
+    if env::var("STY").is_ok_and(|v| !v.is_empty()) {
+        return format!("\x1bP{}\x1b\\", sequence);
+    }
+    sequence.to_string()
+}
@@ -1,4 +1,5 @@
 use std::env;
+use std::path::PathBuf;
 
 use super::strings::capitalize;
 
@@ -20,3 +21,22 @@ pub fn get_user_lang() -> String {
     }
     "unknown".to_string()
 }
+
+/// `NETERO_CONFIG_DIR`, or `netero` under `$XDG_CONFIG_HOME` (default `~/.config`).
+pub fn config_dir() -> Option<PathBuf> {
+    if let Ok(dir) = env::var("NETERO_CONFIG_DIR")
+        && !dir.trim().is_empty()
+    {
+        return Some(PathBuf::from(dir));
+    }
+    let config = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.trim().is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var("HOME")
+                .ok()
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    Some(config.join("netero"))
+}
@@ -1,13 +1,15 @@
 #![allow(unused)]
 
+pub mod clipboard;
 pub mod env;
 pub mod io;
 pub mod lang;
 pub mod strings;
 pub mod time;
 
-pub use env::{get_user, get_user_lang};
+pub use clipboard::copy_to_clipboard;
+pub use env::{config_dir, get_user, get_user_lang};
 pub use io::{get_stdin, stdin_is_piped};
 pub use lang::normalize_lang_tag;
-pub use strings::capitalize;
+pub use strings::{capitalize, format_size, split_front_matter};
 pub use time::current_datetime;
@@ -2,3 +2,80 @@
 pub fn capitalize(s: &str) -> String {
     s.get(0..1).unwrap_or("").to_uppercase() + s.get(1..).unwrap_or("")
 }
+
+/// Formats a byte count with a binary unit (e.g. `12.3 KiB`).
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Splits a leading `---` block of `key: value` lines from the rest of a text.
+/// Keys are lowercased and surrounding quotes are removed from values.
+pub fn split_front_matter(text: &str) -> (Vec<(String, String)>, &str) {
+    let Some(rest) = text
+        .strip_prefix("---\n")
+        .or_else(|| text.strip_prefix("---\r\n"))
+    else {
+        return (Vec::new(), text);
+    };
+    let Some(end) = rest
+        .match_indices("\n---")
+        .map(|(idx, _)| idx)
+        .find(|idx| matches!(rest[idx + 4..].chars().next(), None | Some('\n' | '\r')))
+    else {
+        return (Vec::new(), text);
+    };
+    let fields = rest[..end]
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| {
+            let value = value.trim();
+            let value = value
+                .strip_prefix('"')
+                .and_then(|v| v.strip_suffix('"'))
+                .unwrap_or(value);
+            (key.trim().to_lowercase(), value.to_string())
+        })
+        .collect();
+    let body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+    (fields, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_front_matter_fields_from_the_body() {
+        let text =
+            "---\nDescription: \"Review: a diff\"\nvars: diff, focus=bugs\n---\n\nBody {{diff}}\n";
+        let (fields, body) = split_front_matter(text);
+        assert_eq!(
+            fields,
+            [
+                ("description".to_string(), "Review: a diff".to_string()),
+                ("vars".to_string(), "diff, focus=bugs".to_string()),
+            ]
+        );
+        assert_eq!(body, "Body {{diff}}\n");
+    }
+
+    #[test]
+    fn text_without_closed_front_matter_is_all_body() {
+        assert_eq!(split_front_matter("plain text").1, "plain text");
+        let open = "---\nmodel: x\nno closing line";
+        assert!(split_front_matter(open).0.is_empty());
+        assert_eq!(split_front_matter(open).1, open);
+        // A longer dash run is not the closing line.
+        assert!(split_front_matter("---\na: b\n----\nrest").0.is_empty());
+    }
+}